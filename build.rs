@@ -0,0 +1,50 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Packs everything under assets/ into a single zlib-compressed archive:
+//
+//     "ZPAK" zlib(count:u32 { path_len:u32 path data_len:u32 data }*)
+//
+// All integers are little-endian and paths are stored as the game loads
+// them, e.g. "assets/player/Idle.png". The archive is written to OUT_DIR for
+// the `embed-assets` feature, and copied to $ASSETS_PAK when that is set.
+fn main() {
+    println!("cargo:rerun-if-changed=assets");
+    println!("cargo:rerun-if-env-changed=ASSETS_PAK");
+
+    let mut files = vec![];
+    collect(Path::new("assets"), &mut files);
+    files.sort();
+
+    let mut payload = vec![];
+    payload.extend_from_slice(&(files.len() as u32).to_le_bytes());
+    for path in &files {
+        let name = path.to_str().expect("asset paths must be UTF-8").replace('\\', "/");
+        let data = fs::read(path).unwrap_or_else(|e| panic!("{}: {}", name, e));
+        payload.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        payload.extend_from_slice(&data);
+    }
+
+    let mut archive = b"ZPAK".to_vec();
+    archive.extend_from_slice(&miniz_oxide::deflate::compress_to_vec_zlib(&payload, 9));
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("assets.pak");
+    fs::write(&out, &archive).unwrap();
+    if let Ok(copy) = env::var("ASSETS_PAK") {
+        fs::write(&copy, &archive).unwrap_or_else(|e| panic!("{}: {}", copy, e));
+    }
+}
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+// Miniquad plugin backing the quad-storage crate with window.localStorage.
+// Needs sapp_jsutils.js loaded first for the string objects.
+(function () {
+    "use strict";
+
+    function register_plugin(importObject) {
+        importObject.env.quad_storage_length = function () {
+            return window.localStorage.length;
+        }
+        importObject.env.quad_storage_has_key = function (i) {
+            return window.localStorage.key(i) != null ? 1 : 0;
+        }
+        importObject.env.quad_storage_key = function (i) {
+            return js_object(window.localStorage.key(i));
+        }
+        importObject.env.quad_storage_has_value = function (key) {
+            return window.localStorage.getItem(get_js_object(key)) != null ? 1 : 0;
+        }
+        importObject.env.quad_storage_get = function (key) {
+            return js_object(window.localStorage.getItem(get_js_object(key)));
+        }
+        importObject.env.quad_storage_set = function (key, value) {
+            window.localStorage.setItem(get_js_object(key), get_js_object(value));
+        }
+        importObject.env.quad_storage_remove = function (key) {
+            window.localStorage.removeItem(get_js_object(key));
+        }
+        importObject.env.quad_storage_clear = function () {
+            window.localStorage.clear();
+        }
+    }
+    // quad-storage-sys 0.1.0, encoded as (major << 24) + (minor << 16) + patch.
+    miniquad_add_plugin({ register_plugin, version: 65536, name: "quad_storage" });
+})();
@@ -0,0 +1,231 @@
+use macroquad::prelude::*;
+
+// Gameplay hooks placed on sprite-sheet frames, so retiming a sheet moves
+// the gameplay with it instead of breaking it.
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnimEvent {
+    Fire,
+    Hit,
+    Footstep,
+}
+
+impl AnimEvent {
+    const ALL: [AnimEvent; 3] = [AnimEvent::Fire, AnimEvent::Hit, AnimEvent::Footstep];
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "fire" => Some(AnimEvent::Fire),
+            "hit" => Some(AnimEvent::Hit),
+            "footstep" => Some(AnimEvent::Footstep),
+            _ => None,
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+// The events raised by one animation update, drained by iterating.
+#[derive(Clone, Copy, Default)]
+pub struct AnimEvents(u8);
+
+impl Iterator for AnimEvents {
+    type Item = AnimEvent;
+
+    fn next(&mut self) -> Option<AnimEvent> {
+        let event = AnimEvent::ALL.into_iter().find(|e| self.0 & e.bit() != 0)?;
+        self.0 &= !event.bit();
+        Some(event)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoopMode {
+    Loop,
+    // Stops on the last frame.
+    Once,
+}
+
+// ---------------------------
+// SpriteSheet
+// ---------------------------
+// A horizontal strip of equally sized frames plus its `.anim` metadata, which
+// sits next to the image and looks like:
+//
+//     frames 4
+//     duration 0.1
+//     durations 0.1 0.1 0.05 0.1
+//     loop once
+//     event 2 fire
+//
+// `duration` sets every frame, `durations` overrides them one by one, `loop`
+// is `loop` or `once`, and each `event` raises an `AnimEvent` (`fire`, `hit`
+// or `footstep`) when the animation enters that frame.
+pub struct SpriteSheet {
+    texture: Texture2D,
+    durations: Vec<f32>,
+    mode: LoopMode,
+    // Indexed by frame.
+    events: Vec<AnimEvents>,
+}
+
+impl SpriteSheet {
+    // Builds a sheet from an encoded image and the contents of its `.anim`.
+    pub fn from_bytes(image: &[u8], metadata: &[u8]) -> Result<Self, macroquad::Error> {
+        let image = Image::from_file_with_format(image, None)?;
+        let metadata = std::str::from_utf8(metadata).map_err(|_| "anim: not valid UTF-8")?;
+        let (durations, mode, events) = parse_metadata(metadata)?;
+
+        Ok(Self {
+            texture: Texture2D::from_image(&image),
+            durations,
+            mode,
+            events,
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.durations.len()
+    }
+
+    pub fn draw(&self, frame: usize, x: f32, y: f32, size: Vec2, color: Color, flip_x: bool) {
+        let frame = frame.min(self.frame_count() - 1);
+        let frame_width = self.texture.width() / self.frame_count() as f32;
+        let src = Rect::new(frame as f32 * frame_width, 0.0, frame_width, self.texture.height());
+
+        draw_texture_ex(
+            &self.texture,
+            x,
+            y,
+            color,
+            DrawTextureParams {
+                source: Some(src),
+                dest_size: Some(size),
+                flip_x,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+pub fn metadata_path(path: &str) -> String {
+    match path.rfind('.') {
+        Some(dot) => format!("{}.anim", &path[..dot]),
+        None => format!("{}.anim", path),
+    }
+}
+
+type Metadata = (Vec<f32>, LoopMode, Vec<AnimEvents>);
+
+fn parse_metadata(source: &str) -> Result<Metadata, &'static str> {
+    let mut frames = None;
+    let mut duration = 0.1;
+    let mut durations = None;
+    let mut mode = LoopMode::Loop;
+    let mut events = vec![];
+
+    for line in source.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(key) = words.next() else {
+            continue;
+        };
+
+        match key {
+            "frames" => frames = Some(parse_word::<usize>(words.next())?),
+            "duration" => duration = parse_word(words.next())?,
+            "durations" => durations = Some(words.map(|w| parse_word(Some(w))).collect::<Result<Vec<f32>, _>>()?),
+            "loop" => {
+                mode = match words.next() {
+                    Some("loop") => LoopMode::Loop,
+                    Some("once") => LoopMode::Once,
+                    _ => return Err("anim: loop must be `loop` or `once`"),
+                }
+            }
+            "event" => {
+                let frame: usize = parse_word(words.next())?;
+                let name = words.next().ok_or("anim: event is missing a name")?;
+                let event = AnimEvent::parse(name).ok_or("anim: unknown event")?;
+                events.push((frame, event));
+            }
+            _ => return Err("anim: unknown key"),
+        }
+    }
+
+    let frames = frames.ok_or("anim: missing frame count")?;
+    let durations = durations.unwrap_or_else(|| vec![duration; frames]);
+    if frames == 0 || durations.len() != frames {
+        return Err("anim: durations don't match the frame count");
+    }
+    let mut frame_events = vec![AnimEvents::default(); frames];
+    for (frame, event) in events {
+        let slot = frame_events.get_mut(frame).ok_or("anim: event frame out of range")?;
+        slot.0 |= event.bit();
+    }
+
+    Ok((durations, mode, frame_events))
+}
+
+fn parse_word<T: std::str::FromStr>(word: Option<&str>) -> Result<T, &'static str> {
+    word.and_then(|w| w.parse().ok()).ok_or("anim: expected a number")
+}
+
+// ---------------------------
+// Animation
+// ---------------------------
+// Playback position within a sprite sheet. The sheet is passed in on every
+// call so one entity can switch sheets without rebuilding its state.
+pub struct Animation {
+    frame: usize,
+    timer: f32,
+    finished: bool,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Self {
+            frame: 0,
+            timer: 0.0,
+            finished: false,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // Advances the animation, returning the events of the frame it moved
+    // onto, if any.
+    pub fn update(&mut self, sheet: &SpriteSheet, dt: f32) -> AnimEvents {
+        if self.finished {
+            return AnimEvents::default();
+        }
+
+        self.timer += dt;
+        let frame = self.frame.min(sheet.frame_count() - 1);
+        if self.timer <= sheet.durations[frame] {
+            return AnimEvents::default();
+        }
+        self.timer = 0.0;
+
+        if frame + 1 < sheet.frame_count() {
+            self.frame = frame + 1;
+        } else if sheet.mode == LoopMode::Loop {
+            self.frame = 0;
+        } else {
+            self.frame = frame;
+            self.finished = true;
+            return AnimEvents::default();
+        }
+        sheet.events[self.frame]
+    }
+}
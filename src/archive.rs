@@ -0,0 +1,52 @@
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+use std::collections::HashMap;
+use std::ops::Range;
+
+// ---------------------------
+// Archive
+// ---------------------------
+// The packed asset archive written by build.rs, unpacked into memory.
+pub struct Archive {
+    data: Vec<u8>,
+    entries: HashMap<String, Range<usize>>,
+}
+
+impl Archive {
+    pub fn parse(bytes: &[u8]) -> Result<Self, &'static str> {
+        let compressed = bytes.strip_prefix(b"ZPAK").ok_or("not an asset archive")?;
+        let data = decompress_to_vec_zlib(compressed).map_err(|_| "asset archive is corrupt")?;
+
+        let mut entries = HashMap::new();
+        let mut cursor = 0;
+        let count = read_u32(&data, &mut cursor)?;
+        for _ in 0..count {
+            let path = read_slice(&data, &mut cursor)?;
+            let path = std::str::from_utf8(&data[path]).map_err(|_| "asset archive is corrupt")?;
+            let file = read_slice(&data, &mut cursor)?;
+            entries.insert(path.to_string(), file);
+        }
+
+        Ok(Self { data, entries })
+    }
+
+    pub fn get(&self, path: &str) -> Option<&[u8]> {
+        self.entries.get(path).map(|range| &self.data[range.clone()])
+    }
+}
+
+fn read_u32(data: &[u8], cursor: &mut usize) -> Result<usize, &'static str> {
+    let bytes = data.get(*cursor..*cursor + 4).ok_or("asset archive is truncated")?;
+    *cursor += 4;
+    Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+}
+
+// A length-prefixed run of bytes, returned as its range within `data`.
+fn read_slice(data: &[u8], cursor: &mut usize) -> Result<Range<usize>, &'static str> {
+    let len = read_u32(data, cursor)?;
+    let range = *cursor..*cursor + len;
+    if range.end > data.len() {
+        return Err("asset archive is truncated");
+    }
+    *cursor = range.end;
+    Ok(range)
+}
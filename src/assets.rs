@@ -0,0 +1,421 @@
+use macroquad::prelude::*;
+use macroquad::audio::*;
+use macroquad::experimental::coroutines::{start_coroutine, Coroutine};
+use std::marker::PhantomData;
+use std::ops::Index;
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::SystemTime;
+
+use crate::animation::{metadata_path, SpriteSheet};
+use crate::archive::Archive;
+use crate::sound::{load_wav, splash_sound, Playback, StereoSound};
+use crate::view::{View, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+#[derive(Clone, Copy)]
+enum Kind {
+    SpriteSheet,
+    Texture,
+    Sound,
+    // Carries its handle index, which keys the sound's voices.
+    StereoSound(Playback, usize),
+}
+
+enum Asset {
+    SpriteSheet(SpriteSheet),
+    Texture(Texture2D),
+    Sound(Sound),
+    StereoSound(StereoSound),
+}
+
+// ---------------------------
+// Handle
+// ---------------------------
+// Names one asset in the registry; index `Assets` with it to get the asset.
+pub struct Handle<T> {
+    index: usize,
+    path: &'static str,
+    _asset: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    const fn new(index: usize, path: &'static str) -> Self {
+        Self {
+            index,
+            path,
+            _asset: PhantomData,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+pub const PLAYER_IDLE: Handle<SpriteSheet> = Handle::new(0, "assets/player/Idle.png");
+pub const PLAYER_WALK: Handle<SpriteSheet> = Handle::new(1, "assets/player/Walk.png");
+pub const PLAYER_RUN: Handle<SpriteSheet> = Handle::new(2, "assets/player/Run.png");
+pub const PLAYER_SHOT: Handle<SpriteSheet> = Handle::new(3, "assets/player/Shot.png");
+pub const PLAYER_RECHARGE: Handle<SpriteSheet> = Handle::new(4, "assets/player/Recharge.png");
+pub const PLAYER_DEATH: Handle<SpriteSheet> = Handle::new(5, "assets/player/Dead.png");
+pub const ENEMY_WALK: Handle<SpriteSheet> = Handle::new(6, "assets/enemy/Walk.png");
+pub const ENEMY_DEATH: Handle<SpriteSheet> = Handle::new(7, "assets/enemy/Death.png");
+pub const ENEMY_ATTACK: Handle<SpriteSheet> = Handle::new(8, "assets/enemy/Attack.png");
+
+pub const AMMO: Handle<Texture2D> = Handle::new(0, "assets/game/ammo.png");
+
+pub const PLAYER_DEATH_SOUND: Handle<Sound> = Handle::new(0, "assets/sound/player_death.wav");
+pub const RECHARGING: Handle<Sound> = Handle::new(1, "assets/sound/recharging.wav");
+// Synthesized at load time rather than read from disk, so it isn't in the
+// manifest and comes after the loaded sounds.
+pub const SPLASH: Handle<Sound> = Handle::new(2, "splash (synthesized)");
+
+// Anything heard from a position in the world loads as a StereoSound so it
+// can be panned.
+pub const RAIN: Handle<StereoSound> = Handle::new(0, "assets/sound/rain.wav");
+pub const THUNDER: Handle<StereoSound> = Handle::new(1, "assets/sound/thunder.wav");
+pub const SHOOT: Handle<StereoSound> = Handle::new(2, "assets/sound/shoot.wav");
+pub const ZOMBIE_ATTACK: Handle<StereoSound> = Handle::new(3, "assets/sound/zombie_attack.wav");
+// The attack snarl slowed down makes a passable idle groan.
+pub const ZOMBIE_GROAN: Handle<StereoSound> = Handle::new(4, "assets/sound/zombie_attack.wav");
+
+// Everything the game loads from disk. Each list is in handle order, which
+// the checks below enforce at compile time.
+const SPRITE_SHEETS: [Handle<SpriteSheet>; 9] = [
+    PLAYER_IDLE,
+    PLAYER_WALK,
+    PLAYER_RUN,
+    PLAYER_SHOT,
+    PLAYER_RECHARGE,
+    PLAYER_DEATH,
+    ENEMY_WALK,
+    ENEMY_DEATH,
+    ENEMY_ATTACK,
+];
+const TEXTURES: [Handle<Texture2D>; 1] = [AMMO];
+const SOUNDS: [Handle<Sound>; 2] = [PLAYER_DEATH_SOUND, RECHARGING];
+// Paired with how each one is played back.
+const STEREO_SOUNDS: [(Handle<StereoSound>, Playback); 5] = [
+    (RAIN, Playback { pitch: 1.0, variation: 0.0, voices: 1 }),
+    (THUNDER, Playback { pitch: 1.0, variation: 0.0, voices: 2 }),
+    (SHOOT, Playback { pitch: 1.0, variation: 0.04, voices: 3 }),
+    (ZOMBIE_ATTACK, Playback { pitch: 1.0, variation: 0.08, voices: 3 }),
+    (ZOMBIE_GROAN, Playback { pitch: 0.6, variation: 0.1, voices: 4 }),
+];
+
+const fn in_handle_order<T>(handles: &[Handle<T>]) -> bool {
+    let mut i = 0;
+    while i < handles.len() {
+        if handles[i].index != i {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const _: () = assert!(in_handle_order(&SPRITE_SHEETS));
+const _: () = assert!(in_handle_order(&TEXTURES));
+const _: () = assert!(in_handle_order(&SOUNDS));
+const _: () = assert!(SPLASH.index == SOUNDS.len());
+const _: () = {
+    let mut i = 0;
+    while i < STEREO_SOUNDS.len() {
+        assert!(STEREO_SOUNDS[i].0.index == i);
+        i += 1;
+    }
+};
+
+// Yields each entry's path, how to load it and its handle index.
+fn manifest() -> impl Iterator<Item = (&'static str, Kind, usize)> {
+    let sheets = SPRITE_SHEETS.iter().map(|h| (h.path, Kind::SpriteSheet, h.index));
+    let textures = TEXTURES.iter().map(|h| (h.path, Kind::Texture, h.index));
+    let sounds = SOUNDS.iter().map(|h| (h.path, Kind::Sound, h.index));
+    let stereo_sounds = STEREO_SOUNDS.iter().map(|(h, playback)| (h.path, Kind::StereoSound(*playback, h.index), h.index));
+    sheets.chain(textures).chain(sounds).chain(stereo_sounds)
+}
+
+// Where the web build and non-embedded native builds look for the packed
+// archive that build.rs produces.
+#[cfg(not(feature = "embed-assets"))]
+const ARCHIVE_PATH: &str = "assets.pak";
+
+#[cfg(feature = "embed-assets")]
+async fn open_archive() -> Result<Option<Archive>, String> {
+    let bytes = include_bytes!(concat!(env!("OUT_DIR"), "/assets.pak"));
+    Archive::parse(bytes).map(Some).map_err(|e| format!("embedded assets: {}", e))
+}
+
+// The web build fetches everything as this one file.
+#[cfg(all(not(feature = "embed-assets"), target_arch = "wasm32"))]
+async fn open_archive() -> Result<Option<Archive>, String> {
+    fetch_archive().await.map(Some)
+}
+
+// Native builds load the loose files whenever there's an assets/ folder, so
+// a stale archive can't shadow edits that hot reload would pick up.
+#[cfg(all(not(feature = "embed-assets"), not(target_arch = "wasm32")))]
+async fn open_archive() -> Result<Option<Archive>, String> {
+    if std::path::Path::new("assets").is_dir() {
+        return Ok(None);
+    }
+    let archive = fetch_archive().await?;
+    eprintln!("no assets/ folder, loading assets from {}", ARCHIVE_PATH);
+    Ok(Some(archive))
+}
+
+#[cfg(not(feature = "embed-assets"))]
+async fn fetch_archive() -> Result<Archive, String> {
+    let bytes = load_file(ARCHIVE_PATH).await.map_err(|e| format!("{}: {}", ARCHIVE_PATH, e))?;
+    Archive::parse(&bytes).map_err(|e| format!("{}: {}", ARCHIVE_PATH, e))
+}
+
+// Reads a file out of the archive if it has one, otherwise from disk.
+async fn read(path: &str, archive: Option<&Archive>) -> Result<Vec<u8>, macroquad::Error> {
+    match archive.and_then(|a| a.get(path)) {
+        Some(bytes) => Ok(bytes.to_vec()),
+        None => load_file(path).await,
+    }
+}
+
+async fn load(path: &'static str, kind: Kind, archive: Option<Arc<Archive>>) -> Result<Asset, String> {
+    let archive = archive.as_deref();
+    let asset = async {
+        let bytes = read(path, archive).await?;
+        Ok::<_, macroquad::Error>(match kind {
+            Kind::SpriteSheet => {
+                let metadata = read(&metadata_path(path), archive).await?;
+                Asset::SpriteSheet(SpriteSheet::from_bytes(&bytes, &metadata)?)
+            }
+            Kind::Texture => Asset::Texture(Texture2D::from_image(&Image::from_file_with_format(&bytes, None)?)),
+            Kind::Sound => Asset::Sound(load_wav(&bytes).await?),
+            Kind::StereoSound(playback, key) => Asset::StereoSound(StereoSound::from_bytes(&bytes, playback, key).await?),
+        })
+    };
+    asset.await.map_err(|e| format!("{}: {}", path, e))
+}
+
+// ---------------------------
+// Assets
+// ---------------------------
+// Registry of everything loaded from the manifest. Entities keep handles
+// rather than their own copies, so swapping an asset here updates everyone.
+pub struct Assets {
+    sprite_sheets: Vec<SpriteSheet>,
+    textures: Vec<Texture2D>,
+    sounds: Vec<Sound>,
+    stereo_sounds: Vec<StereoSound>,
+}
+
+impl Assets {
+    // Loads the whole manifest at once, showing a progress bar meanwhile.
+    // Files come from the packed archive when there is one.
+    // On failure returns one line per asset that couldn't be loaded.
+    pub async fn load(view: &View) -> Result<Self, Vec<String>> {
+        let archive = open_archive().await.map_err(|error| vec![error])?.map(Arc::new);
+        let coroutines: Vec<(&str, Coroutine<Result<Asset, String>>)> = manifest()
+            .map(|(path, kind, _)| (path, start_coroutine(load(path, kind, archive.clone()))))
+            .collect();
+
+        loop {
+            let done = coroutines.iter().filter(|(_, c)| c.is_done()).count();
+            draw_loading_screen(view, done as f32 / coroutines.len() as f32);
+            if done == coroutines.len() {
+                break;
+            }
+            next_frame().await;
+        }
+
+        let mut sprite_sheets = Vec::with_capacity(SPRITE_SHEETS.len());
+        let mut textures = Vec::with_capacity(TEXTURES.len());
+        let mut sounds = Vec::with_capacity(SOUNDS.len() + 1);
+        let mut stereo_sounds = Vec::with_capacity(STEREO_SOUNDS.len());
+        let mut errors = vec![];
+        // Results come back in manifest order, which is handle order.
+        for (path, coroutine) in coroutines {
+            match coroutine.retrieve() {
+                Some(Ok(Asset::SpriteSheet(sheet))) => sprite_sheets.push(sheet),
+                Some(Ok(Asset::Texture(texture))) => textures.push(texture),
+                Some(Ok(Asset::Sound(sound))) => sounds.push(sound),
+                Some(Ok(Asset::StereoSound(sound))) => stereo_sounds.push(sound),
+                Some(Err(error)) => errors.push(error),
+                None => errors.push(format!("{}: loading was interrupted", path)),
+            }
+        }
+        match splash_sound().await {
+            Ok(splash) => sounds.push(splash),
+            Err(error) => errors.push(format!("{}: {}", SPLASH.path, error)),
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(Self {
+            sprite_sheets,
+            textures,
+            sounds,
+            stereo_sounds,
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Assets {
+    // Loads every manifest entry at `path` again and swaps it in. The old
+    // asset is kept if the new one fails to load.
+    pub async fn reload(&mut self, path: &'static str) -> Result<(), String> {
+        // Several entries can share a file, e.g. the same sound at two pitches.
+        let entries: Vec<(Kind, usize)> = manifest().filter(|(p, ..)| *p == path).map(|(_, kind, index)| (kind, index)).collect();
+        if entries.is_empty() {
+            return Err(format!("{}: not in the manifest", path));
+        }
+
+        for (kind, index) in entries {
+            // Always from disk: edits land in the loose files, not the archive.
+            match load(path, kind, None).await? {
+                Asset::SpriteSheet(sheet) => self.sprite_sheets[index] = sheet,
+                Asset::Texture(texture) => self.textures[index] = texture,
+                Asset::Sound(sound) => self.sounds[index] = sound,
+                Asset::StereoSound(sound) => self.stereo_sounds[index] = sound,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Index<Handle<SpriteSheet>> for Assets {
+    type Output = SpriteSheet;
+
+    fn index(&self, handle: Handle<SpriteSheet>) -> &SpriteSheet {
+        &self.sprite_sheets[handle.index]
+    }
+}
+
+impl Index<Handle<Texture2D>> for Assets {
+    type Output = Texture2D;
+
+    fn index(&self, handle: Handle<Texture2D>) -> &Texture2D {
+        &self.textures[handle.index]
+    }
+}
+
+impl Index<Handle<Sound>> for Assets {
+    type Output = Sound;
+
+    fn index(&self, handle: Handle<Sound>) -> &Sound {
+        &self.sounds[handle.index]
+    }
+}
+
+impl Index<Handle<StereoSound>> for Assets {
+    type Output = StereoSound;
+
+    fn index(&self, handle: Handle<StereoSound>) -> &StereoSound {
+        &self.stereo_sounds[handle.index]
+    }
+}
+
+// ---------------------------
+// AssetWatcher
+// ---------------------------
+// Polls the modification times of every file in the manifest, including the
+// `.anim` metadata next to sprite sheets, so edits show up in a running game.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct AssetWatcher {
+    timer: f32,
+    files: Vec<(&'static str, String, Option<SystemTime>)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AssetWatcher {
+    const POLL_INTERVAL: f32 = 0.5;
+
+    pub fn new() -> Self {
+        let mut files = vec![];
+        for (path, kind, _) in manifest() {
+            files.push((path, path.to_string(), modified(path)));
+            if let Kind::SpriteSheet = kind {
+                let metadata = metadata_path(path);
+                let time = modified(&metadata);
+                files.push((path, metadata, time));
+            }
+        }
+
+        Self { timer: 0.0, files }
+    }
+
+    // Returns the manifest entries whose files changed since the last poll.
+    pub fn poll(&mut self, dt: f32) -> Vec<&'static str> {
+        let mut changed = vec![];
+        self.timer += dt;
+        if self.timer < Self::POLL_INTERVAL {
+            return changed;
+        }
+        self.timer = 0.0;
+
+        for (entry, file, time) in self.files.iter_mut() {
+            let now = modified(file);
+            if now != *time {
+                *time = now;
+                if !changed.contains(entry) {
+                    changed.push(*entry);
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn draw_loading_screen(view: &View, progress: f32) {
+    view.begin_ui();
+    clear_background(BLACK);
+
+    let width = 400.0;
+    let x = (VIRTUAL_WIDTH - width) / 2.0;
+    let y = VIRTUAL_HEIGHT / 2.0;
+    let title = "Loading...";
+    let title_x = (VIRTUAL_WIDTH - measure_text(title, None, 32, 1.0).width) / 2.0;
+    draw_text(title, title_x, y - 20.0, 32.0, WHITE);
+    draw_rectangle(x, y, width, 20.0, DARKGRAY);
+    draw_rectangle(x + 2.0, y + 2.0, (width - 4.0) * progress, 16.0, RED);
+
+    view.present(0.0);
+}
+
+// Shown instead of the game when assets are missing or broken. Never returns
+// on the web; on native Esc quits.
+pub async fn show_errors(view: &View, errors: &[String]) {
+    loop {
+        view.begin_ui();
+        clear_background(BLACK);
+
+        draw_text("Couldn't load the game's assets:", 60.0, 80.0, 36.0, RED);
+        for (i, error) in errors.iter().enumerate() {
+            draw_text(error, 60.0, 130.0 + i as f32 * 28.0, 24.0, WHITE);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            draw_text("Press Esc to quit", 60.0, VIRTUAL_HEIGHT - 60.0, 24.0, GRAY);
+            if is_key_pressed(KeyCode::Escape) {
+                return;
+            }
+        }
+
+        view.present(0.0);
+        next_frame().await;
+    }
+}
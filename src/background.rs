@@ -0,0 +1,163 @@
+use macroquad::prelude::*;
+
+use crate::view::{GROUND_LEVEL, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const SKYLINE_PARALLAX: f32 = 0.05;
+const TREES_PARALLAX: f32 = 0.12;
+const FENCE_PARALLAX: f32 = 0.25;
+const MARGIN: f32 = 200.0;
+const TILE_SIZE: f32 = 32.0;
+
+struct Building {
+    rect: Rect,
+    windows: Vec<Rect>,
+}
+
+struct Tree {
+    x: f32,
+    width: f32,
+    height: f32,
+}
+
+// ---------------------------
+// Background
+// ---------------------------
+pub struct Background {
+    buildings: Vec<Building>,
+    trees: Vec<Tree>,
+    tile_shades: Vec<f32>,
+    tile_columns: usize,
+}
+
+impl Background {
+    pub fn new() -> Self {
+        let mut buildings = vec![];
+        let mut x = -MARGIN;
+        while x < VIRTUAL_WIDTH + MARGIN {
+            let width = rand::gen_range(60.0, 140.0);
+            let height = rand::gen_range(120.0, 320.0);
+            let rect = Rect::new(x, GROUND_LEVEL - 60.0 - height, width, height);
+
+            let mut windows = vec![];
+            let mut wy = rect.y + 12.0;
+            while wy < rect.y + rect.h - 20.0 {
+                let mut wx = rect.x + 10.0;
+                while wx < rect.x + rect.w - 14.0 {
+                    if rand::gen_range(0.0, 1.0) < 0.08 {
+                        windows.push(Rect::new(wx, wy, 6.0, 9.0));
+                    }
+                    wx += 16.0;
+                }
+                wy += 22.0;
+            }
+
+            buildings.push(Building { rect, windows });
+            x += width + rand::gen_range(4.0, 30.0);
+        }
+
+        let mut trees = vec![];
+        let mut x = -MARGIN;
+        while x < VIRTUAL_WIDTH + MARGIN {
+            trees.push(Tree {
+                x,
+                width: rand::gen_range(40.0, 70.0),
+                height: rand::gen_range(90.0, 170.0),
+            });
+            x += rand::gen_range(25.0, 60.0);
+        }
+
+        let tile_columns = (VIRTUAL_WIDTH / TILE_SIZE).ceil() as usize;
+        let tile_rows = ((VIRTUAL_HEIGHT - GROUND_LEVEL) / TILE_SIZE).ceil() as usize;
+        let tile_shades = (0..tile_columns * tile_rows)
+            .map(|_| rand::gen_range(0.85, 1.15))
+            .collect();
+
+        Self {
+            buildings,
+            trees,
+            tile_shades,
+            tile_columns,
+        }
+    }
+
+    pub fn draw(&self, camera_x: f32, brightness: f32) {
+        self.draw_skyline(-camera_x * SKYLINE_PARALLAX, brightness);
+        self.draw_trees(-camera_x * TREES_PARALLAX, brightness);
+        self.draw_fence(-camera_x * FENCE_PARALLAX, brightness);
+        self.draw_ground(brightness);
+    }
+
+    fn draw_skyline(&self, offset: f32, brightness: f32) {
+        let c = 0.06 + brightness * 0.12;
+        let color = Color::new(c, c, c + 0.04, 1.0);
+        let window = Color::new(0.9, 0.75, 0.35, 0.8 - brightness * 0.5);
+
+        for building in &self.buildings {
+            let r = building.rect;
+            draw_rectangle(r.x + offset, r.y, r.w, r.h, color);
+            for w in &building.windows {
+                draw_rectangle(w.x + offset, w.y, w.w, w.h, window);
+            }
+        }
+    }
+
+    fn draw_trees(&self, offset: f32, brightness: f32) {
+        let c = 0.03 + brightness * 0.08;
+        let color = Color::new(c, c + 0.03, c, 1.0);
+        let base = GROUND_LEVEL - 20.0;
+
+        for tree in &self.trees {
+            let x = tree.x + offset;
+            draw_rectangle(x - 3.0, base - 20.0, 6.0, 20.0, color);
+            // Two stacked triangles give a rough pine silhouette.
+            for (bottom, top, width) in [
+                (base - 15.0, base - tree.height * 0.7, tree.width),
+                (base - tree.height * 0.45, base - tree.height, tree.width * 0.7),
+            ] {
+                draw_triangle(
+                    Vec2::new(x - width / 2.0, bottom),
+                    Vec2::new(x + width / 2.0, bottom),
+                    Vec2::new(x, top),
+                    color,
+                );
+            }
+        }
+    }
+
+    fn draw_fence(&self, offset: f32, brightness: f32) {
+        let c = 0.12 + brightness * 0.2;
+        let color = Color::new(c, c * 0.8, c * 0.6, 1.0);
+        let bottom = GROUND_LEVEL;
+        let height = 50.0;
+        let spacing = 40.0;
+
+        let start = -MARGIN + offset.rem_euclid(spacing);
+        let mut x = start;
+        while x < VIRTUAL_WIDTH + MARGIN {
+            draw_rectangle(x, bottom - height, 6.0, height, color);
+            x += spacing;
+        }
+        draw_rectangle(start, bottom - height + 10.0, VIRTUAL_WIDTH + MARGIN * 2.0, 4.0, color);
+        draw_rectangle(start, bottom - height + 30.0, VIRTUAL_WIDTH + MARGIN * 2.0, 4.0, color);
+    }
+
+    fn draw_ground(&self, brightness: f32) {
+        let base = 0.08 + brightness * 0.15;
+
+        for (i, shade) in self.tile_shades.iter().enumerate() {
+            let col = i % self.tile_columns;
+            let row = i / self.tile_columns;
+            let c = base * shade;
+            draw_rectangle(
+                col as f32 * TILE_SIZE,
+                GROUND_LEVEL + row as f32 * TILE_SIZE,
+                TILE_SIZE,
+                TILE_SIZE,
+                Color::new(c * 0.9, c, c * 0.7, 1.0),
+            );
+        }
+
+        let edge = 0.12 + brightness * 0.2;
+        draw_rectangle(0.0, GROUND_LEVEL - 2.0, VIRTUAL_WIDTH, 4.0, Color::new(edge * 0.8, edge, edge * 0.6, 1.0));
+    }
+}
@@ -0,0 +1,81 @@
+use macroquad::prelude::*;
+
+// Macroquad clamps a single draw call to 10000 vertices / 5000 indices, so
+// the batch flushes itself a little before reaching either limit.
+const MAX_VERTICES: usize = 4000;
+const MAX_INDICES: usize = 4800;
+
+// ---------------------------
+// Batch
+// ---------------------------
+// Collects many small untextured shapes into one mesh and draws them in a
+// single call. The buffers are kept between frames, so building a batch
+// doesn't allocate once it has warmed up.
+pub struct Batch {
+    mesh: Mesh,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self {
+            mesh: Mesh {
+                vertices: Vec::with_capacity(MAX_VERTICES),
+                indices: Vec::with_capacity(MAX_INDICES),
+                texture: None,
+            },
+        }
+    }
+
+    fn reserve(&mut self, vertices: usize, indices: usize) -> u16 {
+        if self.mesh.vertices.len() + vertices > MAX_VERTICES || self.mesh.indices.len() + indices > MAX_INDICES {
+            self.flush();
+        }
+        self.mesh.vertices.len() as u16
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, thickness: f32, color: Color) {
+        let dir = Vec2::new(x2 - x1, y2 - y1).normalize_or_zero();
+        let normal = Vec2::new(-dir.y, dir.x) * thickness / 2.0;
+
+        let base = self.reserve(4, 6);
+        for p in [
+            Vec2::new(x1, y1) + normal,
+            Vec2::new(x1, y1) - normal,
+            Vec2::new(x2, y2) - normal,
+            Vec2::new(x2, y2) + normal,
+        ] {
+            self.mesh.vertices.push(Vertex::new(p.x, p.y, 0.0, 0.0, 0.0, color));
+        }
+        self.mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, w: f32, h: f32, color: Color) {
+        let base = self.reserve(4, 6);
+        for (px, py) in [(x, y), (x + w, y), (x + w, y + h), (x, y + h)] {
+            self.mesh.vertices.push(Vertex::new(px, py, 0.0, 0.0, 0.0, color));
+        }
+        self.mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    pub fn circle(&mut self, x: f32, y: f32, radius: f32, color: Color) {
+        const SIDES: u16 = 8;
+
+        let base = self.reserve(SIDES as usize + 1, SIDES as usize * 3);
+        self.mesh.vertices.push(Vertex::new(x, y, 0.0, 0.0, 0.0, color));
+        for i in 0..SIDES {
+            let angle = i as f32 / SIDES as f32 * std::f32::consts::TAU;
+            self.mesh.vertices.push(Vertex::new(x + angle.cos() * radius, y + angle.sin() * radius, 0.0, 0.0, 0.0, color));
+        }
+        for i in 0..SIDES {
+            self.mesh.indices.extend_from_slice(&[base, base + 1 + i, base + 1 + (i + 1) % SIDES]);
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if !self.mesh.indices.is_empty() {
+            draw_mesh(&self.mesh);
+        }
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+    }
+}
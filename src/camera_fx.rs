@@ -0,0 +1,85 @@
+use macroquad::prelude::*;
+
+// Largest camera displacement at full trauma, in virtual pixels.
+const MAX_SHAKE: f32 = 18.0;
+
+// ---------------------------
+// CameraEffects
+// ---------------------------
+// Trauma-based shake: events add trauma, it decays over time and the shake
+// strength is trauma squared, so small knocks stay subtle.
+pub struct CameraEffects {
+    pub enabled: bool,
+    trauma: f32,
+    hit_stop: f32,
+    chroma: f32,
+    time: f32,
+}
+
+impl CameraEffects {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            trauma: 0.0,
+            hit_stop: 0.0,
+            chroma: 0.0,
+            time: 0.0,
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        if self.enabled {
+            self.trauma = (self.trauma + amount).min(1.0);
+        }
+    }
+
+    pub fn hit_stop(&mut self, duration: f32) {
+        if self.enabled {
+            self.hit_stop = self.hit_stop.max(duration);
+        }
+    }
+
+    pub fn chromatic_flash(&mut self, amount: f32) {
+        if self.enabled {
+            self.chroma = self.chroma.max(amount.min(1.0));
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+        if !self.enabled {
+            self.trauma = 0.0;
+            self.hit_stop = 0.0;
+            self.chroma = 0.0;
+        }
+    }
+
+    // Advances the effects by real time and returns the time step gameplay
+    // should use, which is zero while a hit-stop is freezing the action.
+    pub fn update(&mut self, dt: f32) -> f32 {
+        self.time += dt;
+        self.trauma = (self.trauma - dt * 1.2).max(0.0);
+        self.chroma = (self.chroma - dt * 3.0).max(0.0);
+
+        if self.hit_stop > 0.0 {
+            self.hit_stop -= dt;
+            return 0.0;
+        }
+        dt
+    }
+
+    pub fn shake_offset(&self) -> Vec2 {
+        let strength = self.trauma * self.trauma * MAX_SHAKE;
+        // Two incommensurate sines per axis read as noise without needing a
+        // noise function.
+        let t = self.time;
+        Vec2::new(
+            ((t * 47.0).sin() + (t * 31.3).sin()) * 0.5 * strength,
+            ((t * 53.0).cos() + (t * 27.7).sin()) * 0.5 * strength,
+        )
+    }
+
+    pub fn chroma(&self) -> f32 {
+        self.chroma
+    }
+}
@@ -1,6 +1,25 @@
 use macroquad::prelude::*;
-use crate::player::Player;
-use macroquad::audio::*;
+
+use crate::animation::{AnimEvent, Animation};
+use crate::assets::{self, Assets};
+use crate::mixer::{Bus, Mixer};
+use crate::particles::ParticleSystem;
+use crate::player::{Player, SHOT_POINTS};
+use crate::popups::Popups;
+use crate::weather::{Fog, Puddles};
+use crate::view::{GROUND_LEVEL, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+// A kill counts as a headshot when the zombie was within this many pixels of
+// the player's row. It's a label for a precisely lined-up shot, not a test of
+// where the bullet landed.
+const HEADSHOT_TOLERANCE: f32 = 12.0;
+// Each zombie groans at random intervals in this range while on the move.
+const GROAN_INTERVAL: (f32, f32) = (3.0, 8.0);
+const GROAN_VOLUME: f32 = 0.6;
+
+const HEADSHOT_COLOR: Color = Color::new(1.0, 0.25, 0.2, 1.0);
+const SCORE_COLOR: Color = Color::new(1.0, 0.85, 0.3, 1.0);
+const STREAK_COLOR: Color = Color::new(1.0, 0.55, 0.15, 1.0);
 
 #[derive(PartialEq)]
 pub enum EnemyState {
@@ -16,132 +35,122 @@ pub struct Enemy {
     width: f32,
     height: f32,
     speed: f32,
-    pub frame: usize,
-    pub frame_timer: f32,
-    frame_speed: f32,
-    enemy_texture: Texture2D,
-    death_texture: Texture2D,
-    enemy_atack_texture: Texture2D,
+    animation: Animation,
     facing_left: bool,
     pub state: EnemyState,
-    did_attack: bool,
-    zombie_attack: Sound,
+    groan_timer: f32,
 }
 
 impl Enemy {
-    pub fn new(enemy_texture: Texture2D,death_texture: Texture2D,enemy_atack_texture: Texture2D,speed_multiplier: f32, zombie_attack: Sound) -> Self {
-        let ground_level = screen_height() * 0.8;
-        
+    pub fn new(speed_multiplier: f32) -> Self {
         let from_left = rand::gen_range(0.0, 1.0) < 0.5;
         let (x, facing_left) = if from_left {
             (-64.0, false) 
         } else {
-            (screen_width() + 64.0, true)
+            (VIRTUAL_WIDTH + 64.0, true)
         };
         
         Self {
             x,
-            y: rand::gen_range(ground_level / 1.25, screen_height()/1.25 - 64.0),
+            y: rand::gen_range(GROUND_LEVEL / 1.25, VIRTUAL_HEIGHT/1.25 - 64.0),
             width: 64.0,
             height: 64.0,
             speed: rand::gen_range(80.0, 150.0)* speed_multiplier,
-            frame: 0,
-            frame_timer: 0.0,
-            frame_speed: 0.1,
-            enemy_texture,
-            death_texture,
+            animation: Animation::new(),
             facing_left,
             state: EnemyState::Alive,
-            enemy_atack_texture,
-            did_attack: false,
-            zombie_attack,
+            // Start partway through so a wave doesn't groan in unison.
+            groan_timer: rand::gen_range(0.5, GROAN_INTERVAL.1),
         }
     }
 
-    pub fn update(&mut self, dt: f32, player_x: f32, player_y: f32, player: &mut Player) {
+    pub fn update(&mut self, dt: f32, player: &mut Player, assets: &Assets, mixer: &mut Mixer, puddles: &Puddles) {
         match self.state {
             EnemyState::Alive => {
-                let dx = player_x - self.x;
-                let dy = player_y - self.y;
+                let speed = self.speed * puddles.slowdown_at(self.feet());
+                let dx = player.x - self.x;
+                let dy = player.y - self.y;
                 let distance = (dx * dx + dy * dy).sqrt();
                 if distance < 25.0 {
                     self.state = EnemyState::Attacking;
-                    self.frame = 0;
-                    self.frame_timer = 0.0;
-                    self.did_attack = false;
+                    self.animation.restart();
                 } else {
-                    self.x += self.speed * dx / distance * dt;
-                    self.y += self.speed * dy / distance * dt;
+                    self.x += speed * dx / distance * dt;
+                    self.y += speed * dy / distance * dt;
                 }
 
                 self.facing_left = dx < 0.0;
 
-                self.frame_timer += dt;
-                if self.frame_timer > self.frame_speed {
-                    self.frame = (self.frame + 1) % 10;
-                    self.frame_timer = 0.0;
+                self.animation.update(&assets[assets::ENEMY_WALK], dt);
+
+                self.groan_timer -= dt;
+                if self.groan_timer <= 0.0 {
+                    self.groan_timer = rand::gen_range(GROAN_INTERVAL.0, GROAN_INTERVAL.1);
+                    mixer.play_at(&assets[assets::ZOMBIE_GROAN], Bus::Sfx, GROAN_VOLUME, self.feet());
                 }
             }
             EnemyState::Attacking => {
-                self.frame_timer += dt;
-                if self.frame_timer > 0.1 {
-                    self.frame += 1;
-                    self.frame_timer = 0.0;
-
-                    if self.frame == 2 && !self.did_attack {
-                        play_sound(&self.zombie_attack, PlaySoundParams { looped: false, volume: 1.0 });
-                        player.hit(); 
-                        self.did_attack = true;
-                    }
-
-                    if self.frame >= 5 {
-                        self.state = EnemyState::Alive;
-                        self.frame = 0;
-                        self.did_attack = false;
+                for event in self.animation.update(&assets[assets::ENEMY_ATTACK], dt) {
+                    if event == AnimEvent::Hit {
+                        mixer.play_at(&assets[assets::ZOMBIE_ATTACK], Bus::Sfx, 1.0, self.feet());
+                        player.hit(assets, mixer);
                     }
                 }
+                if self.animation.is_finished() {
+                    self.state = EnemyState::Alive;
+                    self.animation.restart();
+                }
             }
             EnemyState::Dying => {
-                self.frame_timer += dt;
-                if self.frame_timer > self.frame_speed {
-                    self.frame += 1;
-                    self.frame_timer = 0.0;
-                    if self.frame >= 9 {
-                        self.state = EnemyState::Dead;
-                    }
+                self.animation.update(&assets[assets::ENEMY_DEATH], dt);
+                if self.animation.is_finished() {
+                    self.state = EnemyState::Dead;
                 }
             }
             _ => {}
         }
     }
 
-    pub fn draw(&self) {
-        let (texture, frame, total_frames) = match self.state {
-            EnemyState::Alive => (self.enemy_texture.clone(), self.frame, 10),
-            EnemyState::Dying => (self.death_texture.clone(), self.frame, 9),
-            EnemyState::Attacking => (self.enemy_atack_texture.clone(), self.frame, 5),
+    pub fn draw(&self, assets: &Assets, alpha: f32) {
+        let sheet = match self.state {
+            EnemyState::Alive => assets::ENEMY_WALK,
+            EnemyState::Dying => assets::ENEMY_DEATH,
+            EnemyState::Attacking => assets::ENEMY_ATTACK,
             EnemyState::Dead => return,
         };
 
-        let frame_width = texture.width() / total_frames as f32;
-        let src = Rect::new(frame as f32 * frame_width, 0.0, frame_width, texture.height());
-
-        draw_texture_ex(
-            &texture,
+        assets[sheet].draw(
+            self.animation.frame(),
             self.x,
             self.y,
-            WHITE,
-            DrawTextureParams {
-                source: Some(src),
-                dest_size: Some(Vec2::new(self.width * 2.5, self.height * 2.5)),
-                flip_x: self.facing_left,
-                ..Default::default()
-            },
+            Vec2::new(self.width * 2.5, self.height * 2.5),
+            Color::new(1.0, 1.0, 1.0, alpha),
+            self.facing_left,
         );
     }
 
+    pub fn is_alive(&self) -> bool {
+        self.state == EnemyState::Alive || self.state == EnemyState::Attacking
+    }
+
+    pub fn kill(&mut self, direction: Vec2, particles: &mut ParticleSystem) {
+        self.state = EnemyState::Dying;
+        self.animation.restart();
+
+        let center = Vec2::new(self.x + self.width * 1.25, self.y + self.height * 1.1);
+        particles.blood(center, direction, self.feet().y);
+    }
+
+    pub fn head(&self) -> Vec2 {
+        Vec2::new(self.x + self.width * 1.25, self.y + self.height * 0.5)
+    }
+
+    pub fn feet(&self) -> Vec2 {
+        Vec2::new(self.x + self.width * 1.25, self.y + self.height * 2.3)
+    }
+
     pub fn is_off_screen(&self) -> bool {
-        self.x < -100.0 || self.x > screen_width() + 100.0
+        self.x < -100.0 || self.x > VIRTUAL_WIDTH + 100.0
     }
 }
 
@@ -152,31 +161,23 @@ pub struct EnemyManager {
     enemies: Vec<Enemy>,
     spawn_timer: f32,
     spawn_cooldown: f32,
-    enemy_texture: Texture2D,
-    death_texture: Texture2D,
-    enemy_atack_texture: Texture2D,
     difficulty_timer: f32,
     difficulty_level: u32,
-    zombie_attack: Sound,
 }
 
 
 impl EnemyManager {
-    pub fn new(enemy_texture: Texture2D, death_texture: Texture2D,enemy_atack_texture: Texture2D,zombie_attack: Sound) -> Self {
+    pub fn new() -> Self {
         Self {
             enemies: vec![],
             spawn_timer: 0.0,
             spawn_cooldown: 3.0,
-            enemy_texture,
-            death_texture,
-            enemy_atack_texture,
             difficulty_timer: 0.0,
             difficulty_level:1,
-            zombie_attack,
         }
     }
 
-    pub fn update(&mut self, dt: f32, player: &mut Player) {
+    pub fn update(&mut self, dt: f32, player: &mut Player, assets: &Assets, mixer: &mut Mixer, puddles: &Puddles) {
         self.spawn_timer += dt;
 
         self.difficulty_timer += dt;
@@ -188,24 +189,41 @@ impl EnemyManager {
         }
 
         if self.spawn_timer > self.spawn_cooldown {
-            self.enemies.push(Enemy::new(self.enemy_texture.clone(), self.death_texture.clone(), self.enemy_atack_texture.clone(),1.0 + (self.difficulty_level as f32 * 0.1), self.zombie_attack.clone()));
+            self.enemies.push(Enemy::new(1.0 + (self.difficulty_level as f32 * 0.1)));
             self.spawn_timer = 0.0;
         }
 
         for enemy in self.enemies.iter_mut() {
-            enemy.update(dt, player.x, player.y,player);
+            enemy.update(dt, player, assets, mixer, puddles);
         }
+        self.enemies.retain(|e| e.state != EnemyState::Dead && !e.is_off_screen());
+    }
+
+    // Resolves the player's shot, if they fired this frame.
+    pub fn shoot(&mut self, player: &mut Player, particles: &mut ParticleSystem, popups: &mut Popups) {
         if player.did_shoot {
+            // Points come with the shot, hit or miss.
+            let gun = player.gun_position();
+            popups.spawn(gun - Vec2::new(0.0, 24.0), SCORE_COLOR, 22.0, format_args!("+{}", SHOT_POINTS));
+
             for enemy in self.enemies.iter_mut() {
-                if enemy.state == EnemyState::Alive || enemy.state == EnemyState::Attacking{
+                if enemy.is_alive() {
                     let in_range_x = player.x - enemy.x <= 400.0 && player.x - enemy.x >= -400.0;
                     let in_range_y = (player.y - enemy.y).abs() < 40.0;
                     let facing_correct = player.facing_left == (enemy.x < player.x);
 
                     if in_range_x && in_range_y && facing_correct {
-                        enemy.state = EnemyState::Dying;
-                        enemy.frame = 0;
-                        enemy.frame_timer = 0.0;
+                        let direction = Vec2::new(if player.facing_left { -1.0 } else { 1.0 }, 0.0);
+                        enemy.kill(direction, particles);
+
+                        let head = enemy.head();
+                        let streak = player.add_kill();
+                        if streak > 1 {
+                            popups.spawn(head, STREAK_COLOR, 30.0, format_args!("x{} COMBO", streak));
+                        }
+                        if (player.y - enemy.y).abs() < HEADSHOT_TOLERANCE {
+                            popups.spawn(head - Vec2::new(0.0, 28.0), HEADSHOT_COLOR, 34.0, format_args!("HEADSHOT"));
+                        }
                         break;
                     }
                 }
@@ -213,7 +231,23 @@ impl EnemyManager {
 
             player.did_shoot = false;
         }
-        self.enemies.retain(|e| e.state != EnemyState::Dead && !e.is_off_screen());
+    }
+
+    pub fn strike(&mut self, point: Vec2, radius: f32, particles: &mut ParticleSystem) {
+        for enemy in self.enemies.iter_mut() {
+            if enemy.is_alive() && enemy.feet().distance(point) < radius {
+                enemy.kill(Vec2::new(0.0, -1.0), particles);
+            }
+        }
+    }
+
+    pub fn difficulty_level(&self) -> u32 {
+        self.difficulty_level
+    }
+
+    // Counts the zombies still coming for the player within `radius` of `point`.
+    pub fn alive_near(&self, point: Vec2, radius: f32) -> usize {
+        self.enemies.iter().filter(|e| e.is_alive() && e.feet().distance(point) < radius).count()
     }
 
     pub fn reset(&mut self) {
@@ -224,9 +258,9 @@ impl EnemyManager {
         self.spawn_cooldown = 3.0;
     }
 
-    pub fn draw(&self) {
+    pub fn draw(&self, assets: &Assets, player: &Player, fog: &Fog) {
         for enemy in &self.enemies {
-            enemy.draw();
+            enemy.draw(assets, fog.visibility(enemy.feet().distance(player.feet())));
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use macroquad::prelude::*;
+use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use std::f32::consts::TAU;
+
+use crate::view::{VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
+const ARC_SEGMENTS: usize = 32;
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+attribute vec4 normal;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}"#;
+
+// The default macroquad shader with a custom blend mode.
+pub fn blend_material(blend: BlendState) -> Material {
+    load_material(
+        ShaderSource::Glsl {
+            vertex: VERTEX_SHADER,
+            fragment: FRAGMENT_SHADER,
+        },
+        MaterialParams {
+            pipeline_params: PipelineParams {
+                color_blend: Some(blend),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .unwrap()
+}
+
+// ---------------------------
+// Lighting
+// ---------------------------
+pub struct Lighting {
+    target: RenderTarget,
+    camera: Camera2D,
+    additive: Material,
+    multiply: Material,
+    mesh: Mesh,
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        let target = render_target(VIRTUAL_WIDTH as u32, VIRTUAL_HEIGHT as u32);
+        target.texture.set_filter(FilterMode::Linear);
+
+        let camera = Camera2D {
+            target: vec2(VIRTUAL_WIDTH / 2.0, VIRTUAL_HEIGHT / 2.0),
+            zoom: vec2(2.0 / VIRTUAL_WIDTH, 2.0 / VIRTUAL_HEIGHT),
+            render_target: Some(target.clone()),
+            ..Default::default()
+        };
+
+        // Lights add up inside the light map...
+        let additive = blend_material(BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One));
+        // ...and the light map multiplies whatever the scene drew underneath.
+        let multiply = blend_material(BlendState::new(
+            Equation::Add,
+            BlendFactor::Value(BlendValue::DestinationColor),
+            BlendFactor::Zero,
+        ));
+
+        Self {
+            target,
+            camera,
+            additive,
+            multiply,
+            mesh: Mesh {
+                vertices: Vec::with_capacity(ARC_SEGMENTS + 2),
+                indices: Vec::with_capacity(ARC_SEGMENTS * 3),
+                texture: None,
+            },
+        }
+    }
+
+    pub fn begin(&self, ambient: Color) {
+        set_camera(&self.camera);
+        clear_background(ambient);
+        gl_use_material(&self.additive);
+    }
+
+    pub fn end(&self) {
+        gl_use_default_material();
+    }
+
+    pub fn point(&mut self, center: Vec2, radius: f32, color: Color) {
+        self.fan(center, radius, 0.0, TAU, color);
+    }
+
+    pub fn cone(&mut self, origin: Vec2, direction: f32, spread: f32, range: f32, color: Color) {
+        self.fan(origin, range, direction - spread, direction + spread, color);
+    }
+
+    // Triangle fan fading from `color` at the center to black at the rim;
+    // black contributes nothing under additive blending.
+    fn fan(&mut self, center: Vec2, radius: f32, start: f32, end: f32, color: Color) {
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+
+        self.mesh.vertices.push(Vertex::new(center.x, center.y, 0.0, 0.0, 0.0, color));
+        for i in 0..=ARC_SEGMENTS {
+            let angle = start + (end - start) * i as f32 / ARC_SEGMENTS as f32;
+            let x = center.x + angle.cos() * radius;
+            let y = center.y + angle.sin() * radius;
+            self.mesh.vertices.push(Vertex::new(x, y, 0.0, 0.0, 0.0, BLACK));
+        }
+        for i in 0..ARC_SEGMENTS as u16 {
+            self.mesh.indices.extend_from_slice(&[0, i + 1, i + 2]);
+        }
+
+        draw_mesh(&self.mesh);
+    }
+
+    pub fn draw(&self) {
+        gl_use_material(&self.multiply);
+        draw_texture_ex(
+            &self.target.texture,
+            0.0,
+            0.0,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(Vec2::new(VIRTUAL_WIDTH, VIRTUAL_HEIGHT)),
+                ..Default::default()
+            },
+        );
+        gl_use_default_material();
+    }
+}
@@ -1,81 +1,220 @@
 use macroquad::prelude::*;
-use macroquad::audio::*;
 
 mod weather;
 mod player;
 mod enemy;
+mod view;
+mod sound;
+mod background;
+mod lighting;
+mod particles;
+mod batch;
+mod pool;
+mod camera_fx;
+mod animation;
+mod assets;
+mod archive;
+mod mixer;
+mod music;
+mod popups;
+mod settings;
+mod voices;
 
-use weather::{Raindrop, Splash, LightningManager};
+use assets::{show_errors, Assets};
+#[cfg(not(target_arch = "wasm32"))]
+use assets::AssetWatcher;
+use weather::{Fog, Puddles, Rain, LightningManager, WeatherController, STRIKE_RADIUS};
 use player::Player;
 use enemy::EnemyManager;
+use background::Background;
+use camera_fx::CameraEffects;
+use lighting::Lighting;
+use mixer::Mixer;
+use music::Music;
+use particles::ParticleSystem;
+use popups::Popups;
+use settings::Settings;
+use view::{View, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
 
-#[macroquad::main("zombie survival game")]
+const DARKNESS: Color = Color::new(0.1, 0.1, 0.16, 1.0);
+const FLASHLIGHT_COLOR: Color = Color::new(1.0, 0.95, 0.8, 1.0);
+const MUZZLE_COLOR: Color = Color::new(1.0, 0.8, 0.4, 1.0);
+const STRIKE_DAMAGE: i32 = 40;
+
+fn window_conf() -> Conf {
+    Conf {
+        window_title: "zombie survival game".to_owned(),
+        window_width: VIRTUAL_WIDTH as i32,
+        window_height: VIRTUAL_HEIGHT as i32,
+        window_resizable: true,
+        ..Default::default()
+    }
+}
+
+#[macroquad::main(window_conf)]
 async fn main() {
-    let idle_texture = load_texture("assets/player/Idle.png").await.unwrap();
-    let walk_texture = load_texture("assets/player/Walk.png").await.unwrap();
-    let run_texture = load_texture("assets/player/Run.png").await.unwrap();
-    let shot_texture = load_texture("assets/player/Shot.png").await.unwrap();
-    let recharge_texture = load_texture("assets/player/Recharge.png").await.unwrap();
-    let death_texture = load_texture("assets/player/Dead.png").await.unwrap();
-    let enemy_texture = load_texture("assets/enemy/Walk.png").await.unwrap();
-    let enemy_death_texture = load_texture("assets/enemy/Death.png").await.unwrap();
-    let enemy_attack_texture = load_texture("assets/enemy/Attack.png").await.unwrap();
-    let ammo_texture = load_texture("assets/game/ammo.png").await.unwrap();
-
-    let rain_sound = load_sound("assets/sound/rain.wav").await.unwrap();
-    let thunder_sound = load_sound("assets/sound/thunder.wav").await.unwrap();
-    let shoot_sound = load_sound("assets/sound/shoot.wav").await.unwrap();
-    let death_sound = load_sound("assets/sound/player_death.wav").await.unwrap();
-    let zombie_attack_sound = load_sound("assets/sound/zombie_attack.wav").await.unwrap();
-    let recharging_sound = load_sound("assets/sound/recharging.wav").await.unwrap();
-
-    play_sound(&rain_sound, PlaySoundParams { looped: true, volume: 0.5 });
-
-
-    let mut raindrops: Vec<Raindrop> = (0..300).map(|_| Raindrop::new()).collect();
-    let mut splashes: Vec<Splash> = vec![];
-    let mut lightning = LightningManager::new(thunder_sound);
-    let mut player = Player::new(idle_texture.clone(), walk_texture.clone(),run_texture.clone(),shot_texture.clone(),recharge_texture.clone(), death_texture.clone(),ammo_texture.clone(),shoot_sound.clone(), death_sound.clone(), recharging_sound.clone());
-    let mut enemy_manager = EnemyManager::new(enemy_texture, enemy_death_texture, enemy_attack_texture, zombie_attack_sound.clone());  
-    
-    loop {
-        let dt = get_frame_time();
-        lightning.update(dt);
+    let mut view = View::new();
+    let assets = match Assets::load(&view).await {
+        Ok(assets) => assets,
+        Err(errors) => {
+            show_errors(&view, &errors).await;
+            return;
+        }
+    };
 
-        let brightness = if lightning.is_flashing() { 1.0 } else { 0.0 };
-        let bg_color = Color::new(brightness * 0.2, brightness * 0.2, brightness * 0.3, 1.0);
-        clear_background(bg_color);
+    let mut music = match Music::new().await {
+        Ok(music) => music,
+        Err(error) => {
+            show_errors(&view, &[format!("music: {}", error)]).await;
+            return;
+        }
+    };
+
+    let mut settings = Settings::load();
+    let mut camera_fx = CameraEffects::new();
+    camera_fx.enabled = settings.effects;
+    let mut mixer = Mixer::new(&settings);
+    let background = Background::new();
+    let mut lighting = Lighting::new();
+    let mut weather = WeatherController::new(assets[assets::RAIN].clone());
+    let mut fog = Fog::new();
+    let mut puddles = Puddles::new(assets[assets::SPLASH].clone());
+    let mut rain = Rain::new();
+    let mut particles = ParticleSystem::new();
+    let mut popups = Popups::new();
+    let mut lightning = LightningManager::new(assets[assets::THUNDER].clone());
+    let mut player = Player::new();
+    let mut enemy_manager = EnemyManager::new();
 
-        for drop in raindrops.iter_mut() {
-            drop.update(dt);
-            drop.draw(brightness);
-            if drop.is_near_ground() {
-                splashes.push(Splash::new(drop.x, drop.y + drop.length));
-                *drop = Raindrop::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    let (mut assets, mut watcher) = (assets, AssetWatcher::new());
+
+    loop {
+        // Swap in assets edited on disk without restarting the run.
+        #[cfg(not(target_arch = "wasm32"))]
+        for path in watcher.poll(get_frame_time()) {
+            match assets.reload(path).await {
+                Ok(()) if path == assets::RAIN.path() => weather.set_rain_sound(assets[assets::RAIN].clone()),
+                Ok(()) if path == assets::THUNDER.path() => lightning.set_thunder_sound(assets[assets::THUNDER].clone()),
+                // Everything else is looked up through its handle each frame.
+                Ok(()) => {}
+                Err(error) => eprintln!("hot reload failed: {}", error),
             }
         }
 
-        for splash in splashes.iter_mut() {
-            splash.update(dt);
-            splash.draw(brightness);
+        if is_key_pressed(KeyCode::F2) {
+            camera_fx.toggle();
+            settings.effects = camera_fx.enabled;
+            settings.save();
+        }
+        if is_key_pressed(KeyCode::M) {
+            mixer.toggle_mute();
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            mixer.select_next();
+        }
+        if is_key_pressed(KeyCode::Minus) {
+            mixer.adjust(-1.0);
         }
+        if is_key_pressed(KeyCode::Equal) {
+            mixer.adjust(1.0);
+        }
+        mixer.update(get_frame_time());
+        if mixer.store_settled(&mut settings) {
+            settings.save();
+        }
+        mixer.set_listener(player.center());
+        let dt = camera_fx.update(get_frame_time());
+        view.set_offset(camera_fx.shake_offset());
+        weather.update(dt, &mixer);
+        lightning.update(dt, weather.lightning_frequency(), &mut mixer);
+        view.begin();
 
-        splashes.retain(|s| !s.is_dead());
+        let brightness = lightning.brightness();
+        fog.update(dt, weather.wind(), weather.fog_density(), brightness);
+        puddles.update(dt, weather.rain_intensity());
+        let bg_color = Color::new(brightness * 0.2, brightness * 0.2, brightness * 0.3, 1.0);
+        clear_background(bg_color);
 
         lightning.draw();
+        background.draw(player.x - VIRTUAL_WIDTH / 2.0, brightness);
+        puddles.draw(brightness);
+        lightning.draw_ground();
 
-        player.update(dt);  
-        player.draw();
+        rain.update(dt, weather.raindrop_count(), weather.wind(), weather.splash_chance(), &mut particles);
+        rain.draw(brightness);
+
+        player.update(dt, &assets, &mut mixer, &puddles);  
+        player.draw(&assets);
+        if player.did_shoot {
+            particles.muzzle_flash(player.gun_position(), player.facing_left);
+            particles.shell_casing(player.gun_position(), player.facing_left, player.feet().y);
+        }
 
         if !player.is_dead {
-            enemy_manager.update(dt, &mut player);
+            enemy_manager.update(dt, &mut player, &assets, &mut mixer, &puddles);
+            enemy_manager.shoot(&mut player, &mut particles, &mut popups);
+        }
+        for &impact in lightning.impacts() {
+            enemy_manager.strike(impact, STRIKE_RADIUS, &mut particles);
+            let distance = player.feet().distance(impact);
+            if distance < STRIKE_RADIUS {
+                player.damage(STRIKE_DAMAGE, &assets, &mut mixer);
+            }
+            // Strikes nearby rattle the screen, far ones barely register.
+            let closeness = (1.0 - distance / VIRTUAL_WIDTH).clamp(0.0, 1.0);
+            camera_fx.add_trauma(0.7 * closeness * closeness);
+            camera_fx.chromatic_flash(0.6 * closeness);
+        }
+        if player.damage_taken > 0 {
+            let head = player.center() - Vec2::new(0.0, 70.0);
+            popups.spawn(head, Color::new(1.0, 0.2, 0.2, 1.0), 26.0, format_args!("-{}", player.damage_taken));
+            camera_fx.add_trauma(0.25 + player.damage_taken as f32 / 100.0);
+            camera_fx.hit_stop(0.06);
+            camera_fx.chromatic_flash(0.5);
+            player.damage_taken = 0;
+        }
+        music.update(dt, &mixer, &player, &enemy_manager);
+        mixer.flush();
+        enemy_manager.draw(&assets, &player, &fog);
+        particles.update(dt);
+        particles.draw();
+        fog.draw(brightness);
+        lightning.draw_strikes();
+
+        let ambient = Color::new(
+            DARKNESS.r + (1.0 - DARKNESS.r) * brightness,
+            DARKNESS.g + (1.0 - DARKNESS.g) * brightness,
+            DARKNESS.b + (1.0 - DARKNESS.b) * brightness,
+            1.0,
+        );
+        lighting.begin(ambient);
+        let direction = if player.facing_left { std::f32::consts::PI } else { 0.0 };
+        lighting.cone(player.gun_position(), direction, 0.3, 520.0, FLASHLIGHT_COLOR);
+        lighting.point(player.center(), 110.0, Color::new(0.25, 0.25, 0.25, 1.0));
+        lightning.draw_lights(&mut lighting);
+        let flash = player.muzzle_flash();
+        if flash > 0.0 {
+            let c = MUZZLE_COLOR;
+            lighting.point(player.gun_position(), 260.0, Color::new(c.r * flash, c.g * flash, c.b * flash, 1.0));
         }
-        enemy_manager.draw();
-        
+        lighting.end();
+
+        view.begin();
+        lighting.draw();
+        // Popups go on top of the light map so they stay readable in the dark.
+        popups.update(dt);
+        popups.draw();
+        view.begin_ui();
+        player.draw_ui(&assets);
+        mixer.draw_ui();
+
         if player.is_dead {
             if is_key_pressed(KeyCode::R) {
-                player = Player::new(idle_texture.clone(), walk_texture.clone(), run_texture.clone(), shot_texture.clone(), recharge_texture.clone(), death_texture.clone(),ammo_texture.clone(),shoot_sound.clone(), death_sound.clone(), recharging_sound.clone());
+                player = Player::new();
                 enemy_manager.reset();
+                particles.clear();
+                popups.clear();
             }else {
                 #[cfg(not(target_arch = "wasm32"))]
                 if is_key_pressed(KeyCode::Escape) {
@@ -84,7 +223,8 @@ async fn main() {
             }
 
         }
-        
+
+        view.present(camera_fx.chroma());
         next_frame().await;
     }
 }
\ No newline at end of file
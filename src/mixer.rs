@@ -0,0 +1,177 @@
+use macroquad::prelude::*;
+use macroquad::audio::*;
+
+use crate::settings::Settings;
+use crate::sound::StereoSound;
+use crate::view::VIRTUAL_WIDTH;
+use crate::voices::VoiceManager;
+
+pub const CHANNELS: [&str; 4] = ["Master", "Music", "SFX", "Ambience"];
+const VOLUME_STEP: f32 = 0.1;
+
+// Ambience drops to this level while ducked and takes DUCK_RELEASE seconds
+// to come back once the hold runs out.
+const DUCK_LEVEL: f32 = 0.3;
+const DUCK_RELEASE: f32 = 1.5;
+
+// Positional sounds fade out completely this far from the listener and pan
+// fully to one side at half a screen away. Pan stops a little short of hard
+// left/right so the far ear still hears something.
+const HEARING_RANGE: f32 = VIRTUAL_WIDTH;
+const MAX_PAN: f32 = 0.85;
+
+// How long the volume overlay stays up after a change.
+const OVERLAY_TIME: f32 = 2.0;
+
+// Indexes into the mixer levels, after master.
+#[derive(Clone, Copy)]
+pub enum Bus {
+    Music = 1,
+    Sfx = 2,
+    Ambience = 3,
+}
+
+// ---------------------------
+// Mixer
+// ---------------------------
+pub struct Mixer {
+    // Master first, then one level per bus.
+    levels: [f32; 4],
+    muted: bool,
+    selected: usize,
+    duck: f32,
+    duck_hold: f32,
+    overlay_timer: f32,
+    // Levels changed since the overlay opened and not yet saved.
+    unsaved: bool,
+    listener: Vec2,
+    voices: VoiceManager,
+}
+
+impl Mixer {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            levels: settings.levels,
+            muted: settings.muted,
+            selected: 0,
+            duck: 1.0,
+            duck_hold: 0.0,
+            overlay_timer: 0.0,
+            unsaved: false,
+            listener: Vec2::new(VIRTUAL_WIDTH / 2.0, 0.0),
+            voices: VoiceManager::new(),
+        }
+    }
+
+    pub fn gain(&self, bus: Bus) -> f32 {
+        if self.muted {
+            return 0.0;
+        }
+        self.levels[0] * self.levels[bus as usize]
+    }
+
+    // Extra gain for ambience loops that should make room for loud events.
+    pub fn duck_gain(&self) -> f32 {
+        self.duck
+    }
+
+    pub fn play(&self, sound: &Sound, bus: Bus, volume: f32) {
+        play_sound(sound, PlaySoundParams { looped: false, volume: volume * self.gain(bus) });
+    }
+
+    // Stereo one-shots go through the voice manager and start at the next
+    // flush.
+    pub fn play_stereo(&mut self, sound: &StereoSound, bus: Bus, volume: f32, pan: f32) {
+        self.voices.request(sound, volume * self.gain(bus), pan);
+    }
+
+    // Starts the one-shots requested since the last flush; call once a frame.
+    pub fn flush(&mut self) {
+        self.voices.flush();
+    }
+
+    // Where the ears are; positional sounds are heard relative to this.
+    pub fn set_listener(&mut self, position: Vec2) {
+        self.listener = position;
+    }
+
+    // Pans by the sound's horizontal offset from the listener and fades it
+    // with distance.
+    pub fn play_at(&mut self, sound: &StereoSound, bus: Bus, volume: f32, position: Vec2) {
+        let offset = position - self.listener;
+        let pan = (offset.x / (VIRTUAL_WIDTH / 2.0)).clamp(-1.0, 1.0) * MAX_PAN;
+        let falloff = (1.0 - offset.length() / HEARING_RANGE).max(0.0).powi(2);
+        if falloff > 0.0 {
+            self.play_stereo(sound, bus, volume * falloff, pan);
+        }
+    }
+
+    // Pulls the ambience down for at least `hold` seconds.
+    pub fn duck(&mut self, hold: f32) {
+        self.duck = DUCK_LEVEL;
+        self.duck_hold = self.duck_hold.max(hold);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.overlay_timer = (self.overlay_timer - dt).max(0.0);
+        if self.duck_hold > 0.0 {
+            self.duck_hold -= dt;
+        } else {
+            self.duck = (self.duck + dt * (1.0 - DUCK_LEVEL) / DUCK_RELEASE).min(1.0);
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.changed();
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % CHANNELS.len();
+        self.overlay_timer = OVERLAY_TIME;
+    }
+
+    pub fn adjust(&mut self, steps: f32) {
+        let level = &mut self.levels[self.selected];
+        *level = ((*level + steps * VOLUME_STEP) * 10.0).round().clamp(0.0, 10.0) / 10.0;
+        self.changed();
+    }
+
+    fn changed(&mut self) {
+        self.overlay_timer = OVERLAY_TIME;
+        self.unsaved = true;
+    }
+
+    // Copies the levels into `settings` once the overlay has closed after a
+    // change, so a run of key presses is saved once. Returns whether
+    // anything was copied.
+    pub fn store_settled(&mut self, settings: &mut Settings) -> bool {
+        if !self.unsaved || self.overlay_timer > 0.0 {
+            return false;
+        }
+        self.unsaved = false;
+        settings.levels = self.levels;
+        settings.muted = self.muted;
+        true
+    }
+
+    pub fn draw_ui(&self) {
+        if self.overlay_timer <= 0.0 {
+            return;
+        }
+        let alpha = (self.overlay_timer / 0.3).min(1.0);
+        let x = VIRTUAL_WIDTH - 260.0;
+        let y = 80.0;
+
+        draw_rectangle(x, y, 220.0, 30.0 + CHANNELS.len() as f32 * 26.0, Color::new(0.0, 0.0, 0.0, 0.6 * alpha));
+        let title = if self.muted { "Audio (muted)" } else { "Audio" };
+        draw_text(title, x + 10.0, y + 22.0, 22.0, Color::new(1.0, 1.0, 1.0, alpha));
+        for (i, name) in CHANNELS.iter().enumerate() {
+            let row = y + 34.0 + i as f32 * 26.0;
+            let color = if i == self.selected { Color::new(1.0, 0.85, 0.3, alpha) } else { Color::new(0.8, 0.8, 0.8, alpha) };
+            draw_text(name, x + 10.0, row + 14.0, 20.0, color);
+            draw_rectangle(x + 100.0, row + 2.0, 100.0, 14.0, Color::new(0.3, 0.3, 0.3, alpha));
+            draw_rectangle(x + 100.0, row + 2.0, 100.0 * self.levels[i], 14.0, color);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use macroquad::prelude::*;
+use macroquad::audio::*;
+use std::f32::consts::TAU;
+
+use crate::enemy::EnemyManager;
+use crate::mixer::{Bus, Mixer};
+use crate::player::Player;
+use crate::sound::encode_wav;
+
+// There are no music files; the stems are synthesized at startup. They all
+// share one loop length and start together, so they stay in step and any mix
+// of them sounds like one piece.
+const SAMPLE_RATE: u32 = 22050;
+const BEAT: f32 = 0.625;
+const LOOP_BEATS: usize = 16;
+
+// Zombies closer than this to the player count towards the threat.
+const THREAT_RADIUS: f32 = 450.0;
+// This many zombies nearby is as tense as it gets.
+const MAX_THREAT: f32 = 6.0;
+// Each difficulty level past the first adds this much tension on its own.
+const LEVEL_THREAT: f32 = 0.08;
+// Health at or below which the danger layer comes in.
+const LOW_HEALTH: i32 = 35;
+// Seconds a stem takes to fade fully in or out.
+const FADE_TIME: f32 = 2.0;
+
+// Stems, from always-on to most intense.
+const PAD: usize = 0;
+const PULSE: usize = 1;
+const DRUMS: usize = 2;
+const DANGER: usize = 3;
+
+// ---------------------------
+// Music
+// ---------------------------
+pub struct Music {
+    stems: [Sound; 4],
+    levels: [f32; 4],
+    wave_stinger: Sound,
+    death_stinger: Sound,
+    difficulty_level: u32,
+    was_dead: bool,
+}
+
+impl Music {
+    pub async fn new() -> Result<Self, macroquad::Error> {
+        let stems = [
+            load_sound_from_bytes(&render(loop_length(), pad)).await?,
+            load_sound_from_bytes(&render(loop_length(), pulse)).await?,
+            load_sound_from_bytes(&render(loop_length(), drums)).await?,
+            load_sound_from_bytes(&render(loop_length(), danger)).await?,
+        ];
+        // Silent until the first update applies the mixer levels.
+        for stem in &stems {
+            play_sound(stem, PlaySoundParams { looped: true, volume: 0.0 });
+        }
+
+        Ok(Self {
+            stems,
+            levels: [0.0; 4],
+            wave_stinger: load_sound_from_bytes(&render(1.4, wave_stinger)).await?,
+            death_stinger: load_sound_from_bytes(&render(3.0, death_stinger)).await?,
+            difficulty_level: 1,
+            was_dead: false,
+        })
+    }
+
+    pub fn update(&mut self, dt: f32, mixer: &Mixer, player: &Player, enemies: &EnemyManager) {
+        let level = enemies.difficulty_level();
+        if level > self.difficulty_level {
+            mixer.play(&self.wave_stinger, Bus::Music, 0.7);
+        }
+        self.difficulty_level = level;
+        if player.is_dead && !self.was_dead {
+            mixer.play(&self.death_stinger, Bus::Music, 0.9);
+        }
+        self.was_dead = player.is_dead;
+
+        let nearby = enemies.alive_near(player.feet(), THREAT_RADIUS) as f32 / MAX_THREAT;
+        let threat = (nearby + (level - 1) as f32 * LEVEL_THREAT).min(1.0);
+        // Everything drops out under the death stinger.
+        let mut targets = [0.0; 4];
+        if !player.is_dead {
+            targets[PAD] = 1.0;
+            targets[PULSE] = ((threat - 0.1) / 0.3).clamp(0.0, 1.0);
+            targets[DRUMS] = ((threat - 0.45) / 0.3).clamp(0.0, 1.0);
+            targets[DANGER] = if player.health() <= LOW_HEALTH { 1.0 } else { 0.0 };
+        }
+
+        let gain = mixer.gain(Bus::Music);
+        for ((stem, level), target) in self.stems.iter().zip(self.levels.iter_mut()).zip(targets) {
+            *level += (target - *level).clamp(-dt / FADE_TIME, dt / FADE_TIME);
+            set_sound_volume(stem, *level * gain);
+        }
+    }
+}
+
+// ---------------------------
+// Synthesis
+// ---------------------------
+fn loop_length() -> f32 {
+    LOOP_BEATS as f32 * BEAT
+}
+
+fn render(seconds: f32, voice: fn(f32) -> f32) -> Vec<u8> {
+    let samples: Vec<i16> = (0..(seconds * SAMPLE_RATE as f32) as usize)
+        .map(|i| (voice(i as f32 / SAMPLE_RATE as f32).clamp(-1.0, 1.0) * 12000.0) as i16)
+        .collect();
+    encode_wav(1, SAMPLE_RATE, &samples)
+}
+
+// A sine that completes a whole number of cycles over the loop, so looping
+// stems don't click at the seam.
+fn tone(frequency: f32, t: f32) -> f32 {
+    let cycles = (frequency * loop_length()).round();
+    (TAU * cycles * t / loop_length()).sin()
+}
+
+// Cheap deterministic noise; the same time always gives the same value, so
+// the percussion repeats exactly each loop.
+fn noise(t: f32) -> f32 {
+    let mut x = (t * SAMPLE_RATE as f32) as u32;
+    x = x.wrapping_mul(0x9E37_79B9) ^ (x >> 15);
+    x = x.wrapping_mul(0x85EB_CA6B) ^ (x >> 13);
+    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+// Low A minor chord that swells and fades once per loop.
+fn pad(t: f32) -> f32 {
+    let swell = 0.6 + 0.4 * tone(1.0 / loop_length(), t);
+    let chord = tone(110.0, t) + tone(130.81, t) * 0.8 + tone(164.81, t) * 0.6 + tone(110.5, t) * 0.5;
+    chord * swell * 0.22
+}
+
+// Plucked bass on every eighth note.
+fn pulse(t: f32) -> f32 {
+    const NOTES: [f32; 8] = [55.0, 55.0, 65.41, 55.0, 55.0, 82.41, 73.42, 65.41];
+    let step = BEAT / 2.0;
+    let index = (t / step) as usize;
+    let local = t - index as f32 * step;
+    let frequency = NOTES[index % NOTES.len()];
+    let phase = TAU * frequency * local;
+    let body = phase.sin() + (phase * 2.0).sin() * 0.5 + (phase * 3.0).sin() * 0.25;
+    body * (-local * 12.0).exp() * 0.5
+}
+
+// Kick on every beat, snare on two and four, hats on the off-beats.
+fn drums(t: f32) -> f32 {
+    let beat = (t / BEAT) as usize;
+    let local = t - beat as f32 * BEAT;
+
+    // The kick drops in pitch as it decays; integrate the sweep for its phase.
+    let kick = (TAU * (45.0 * local + 75.0 / 30.0 * (1.0 - (-local * 30.0).exp()))).sin() * (-local * 14.0).exp();
+    let snare = if beat % 2 == 1 { noise(t) * (-local * 18.0).exp() * 0.5 } else { 0.0 };
+    let off = (local - BEAT / 2.0).max(0.0);
+    let hat = if local >= BEAT / 2.0 { noise(t) * (-off * 60.0).exp() * 0.25 } else { 0.0 };
+    (kick + snare + hat) * 0.6
+}
+
+// High minor-second cluster with a fast tremolo, for when the player is
+// about to die.
+fn danger(t: f32) -> f32 {
+    let tremolo = 0.5 + 0.5 * tone(8.0, t);
+    (tone(440.0, t) + tone(466.16, t)) * tremolo * 0.12
+}
+
+// Rising A minor arpeggio.
+fn wave_stinger(t: f32) -> f32 {
+    const NOTES: [f32; 4] = [220.0, 261.63, 329.63, 440.0];
+    const STEP: f32 = 0.12;
+    NOTES
+        .iter()
+        .enumerate()
+        .map(|(i, frequency)| {
+            let local = t - i as f32 * STEP;
+            if local < 0.0 {
+                return 0.0;
+            }
+            let phase = TAU * frequency * local;
+            (phase.sin() + (phase * 2.0).sin() * 0.3) * (-local * 3.0).exp()
+        })
+        .sum::<f32>()
+        * 0.35
+}
+
+// Two detuned low notes sliding down an octave.
+fn death_stinger(t: f32) -> f32 {
+    // Phase of a frequency falling from f to f / 2 over the stinger.
+    let glide = |frequency: f32| TAU * frequency * (t - t * t / 12.0);
+    let fade = (1.0 - t / 3.0).max(0.0);
+    (glide(110.0).sin() + glide(116.54).sin() + glide(55.0).sin() * 0.7) * fade * fade * 0.35
+}
@@ -0,0 +1,181 @@
+use macroquad::prelude::*;
+
+use crate::batch::Batch;
+use crate::pool::Pool;
+
+const MAX_PARTICLES: usize = 4096;
+
+#[derive(Clone, Copy)]
+pub enum Shape {
+    Circle,
+    // Stretched along the direction of travel.
+    Streak,
+    Square,
+}
+
+#[derive(Clone, Copy)]
+pub struct Particle {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub gravity: f32,
+    pub drag: f32,
+    pub life: f32,
+    pub max_life: f32,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub shape: Shape,
+    // Ground height the particle comes to rest on, if any.
+    pub floor: Option<f32>,
+}
+
+impl Particle {
+    fn new(position: Vec2, velocity: Vec2, life: f32, size: f32, color: Color, shape: Shape) -> Self {
+        Self {
+            position,
+            velocity,
+            gravity: 0.0,
+            drag: 0.0,
+            life,
+            max_life: life,
+            start_size: size,
+            end_size: size,
+            start_color: color,
+            end_color: Color::new(color.r, color.g, color.b, 0.0),
+            shape,
+            floor: None,
+        }
+    }
+
+    fn update(&mut self, dt: f32) {
+        self.life -= dt;
+        self.velocity.y += self.gravity * dt;
+        self.velocity *= 1.0 / (1.0 + self.drag * dt);
+        self.position += self.velocity * dt;
+
+        if let Some(floor) = self.floor {
+            if self.position.y > floor {
+                self.position.y = floor;
+                // Lose most of the energy on each bounce, then settle.
+                self.velocity.y = -self.velocity.y * 0.3;
+                self.velocity.x *= 0.5;
+                if self.velocity.y.abs() < 20.0 {
+                    self.velocity = Vec2::ZERO;
+                }
+            }
+        }
+    }
+
+    fn draw(&self, batch: &mut Batch) {
+        let t = 1.0 - self.life / self.max_life;
+        let size = self.start_size + (self.end_size - self.start_size) * t;
+        let a = self.start_color.to_vec();
+        let b = self.end_color.to_vec();
+        let color = Color::from_vec(a + (b - a) * t);
+
+        match self.shape {
+            Shape::Circle => batch.circle(self.position.x, self.position.y, size, color),
+            Shape::Streak => {
+                let tail = self.position - self.velocity.normalize_or_zero() * size * 3.0;
+                batch.line(tail.x, tail.y, self.position.x, self.position.y, size, color);
+            }
+            Shape::Square => batch.rect(self.position.x - size / 2.0, self.position.y - size / 2.0, size, size, color),
+        }
+    }
+}
+
+// ---------------------------
+// ParticleSystem
+// ---------------------------
+pub struct ParticleSystem {
+    particles: Pool<Particle>,
+    batch: Batch,
+}
+
+impl ParticleSystem {
+    pub fn new() -> Self {
+        Self {
+            particles: Pool::new(MAX_PARTICLES, || {
+                Particle::new(Vec2::ZERO, Vec2::ZERO, 0.0, 0.0, BLANK, Shape::Circle)
+            }),
+            batch: Batch::new(),
+        }
+    }
+
+    pub fn emit(&mut self, particle: Particle) {
+        // When the pool is full new particles are dropped; nobody notices one
+        // missing droplet.
+        if let Some(slot) = self.particles.spawn() {
+            *slot = particle;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.particles.retain(|p| {
+            p.update(dt);
+            p.life > 0.0
+        });
+    }
+
+    pub fn draw(&mut self) {
+        for particle in self.particles.iter() {
+            particle.draw(&mut self.batch);
+        }
+        self.batch.flush();
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    pub fn muzzle_flash(&mut self, position: Vec2, facing_left: bool) {
+        let dir = if facing_left { -1.0 } else { 1.0 };
+
+        let mut flash = Particle::new(position, Vec2::ZERO, 0.06, 14.0, Color::new(1.0, 0.9, 0.5, 0.9), Shape::Circle);
+        flash.end_size = 4.0;
+        self.emit(flash);
+
+        for _ in 0..8 {
+            let velocity = Vec2::new(dir * rand::gen_range(300.0, 700.0), rand::gen_range(-120.0, 120.0));
+            let life = rand::gen_range(0.05, 0.12);
+            let mut spark = Particle::new(position, velocity, life, 2.0, Color::new(1.0, 0.8, 0.3, 1.0), Shape::Streak);
+            spark.drag = 8.0;
+            self.emit(spark);
+        }
+    }
+
+    pub fn shell_casing(&mut self, position: Vec2, facing_left: bool, floor: f32) {
+        let dir = if facing_left { 1.0 } else { -1.0 };
+        let velocity = Vec2::new(dir * rand::gen_range(60.0, 140.0), rand::gen_range(-260.0, -180.0));
+
+        let mut casing = Particle::new(position, velocity, 2.5, 4.0, Color::new(0.85, 0.65, 0.25, 1.0), Shape::Square);
+        casing.gravity = 900.0;
+        casing.floor = Some(floor);
+        casing.end_color = Color::new(0.85, 0.65, 0.25, 0.0);
+        self.emit(casing);
+    }
+
+    pub fn blood(&mut self, position: Vec2, direction: Vec2, floor: f32) {
+        for _ in 0..20 {
+            let spread = Vec2::new(rand::gen_range(-0.6, 0.6), rand::gen_range(-1.0, 0.2));
+            let velocity = (direction + spread).normalize_or_zero() * rand::gen_range(80.0, 320.0);
+            let life = rand::gen_range(0.4, 0.9);
+            let mut drop = Particle::new(position, velocity, life, rand::gen_range(2.0, 4.0), Color::new(0.55, 0.02, 0.02, 1.0), Shape::Circle);
+            drop.gravity = 700.0;
+            drop.floor = Some(floor);
+            drop.end_color = Color::new(0.3, 0.0, 0.0, 0.0);
+            self.emit(drop);
+        }
+    }
+
+    pub fn splash(&mut self, position: Vec2, wind: f32) {
+        for _ in 0..3 {
+            let velocity = Vec2::new(rand::gen_range(-40.0, 40.0) + wind * 0.3, rand::gen_range(-90.0, -40.0));
+            let life = rand::gen_range(0.2, 0.4);
+            let mut droplet = Particle::new(position, velocity, life, 1.0, Color::new(0.8, 0.8, 0.9, 0.8), Shape::Streak);
+            droplet.gravity = 400.0;
+            self.emit(droplet);
+        }
+    }
+}
@@ -1,5 +1,29 @@
 use macroquad::prelude::*;
-use macroquad::audio::*;
+
+use crate::animation::{AnimEvent, Animation, SpriteSheet};
+use crate::assets::{self, Assets, Handle};
+use crate::mixer::{Bus, Mixer};
+use crate::view::{GROUND_LEVEL, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+use crate::weather::Puddles;
+
+const MUZZLE_FLASH_TIME: f32 = 0.08;
+// Seconds the death sting holds the rain down for.
+const DEATH_DUCK: f32 = 3.0;
+// Every shot fired scores this much.
+pub const SHOT_POINTS: u32 = 10;
+// Kills closer together than this keep the streak going. The streak is only
+// shown, it doesn't change the score.
+const STREAK_WINDOW: f32 = 3.0;
+
+const WALK_SPEED: f32 = 300.0;
+const RUN_SPEED: f32 = 500.0;
+const EXHAUSTED_SPEED: f32 = 180.0;
+// Drain and regen are per second; a full bar lasts four seconds of running.
+const MAX_STAMINA: f32 = 100.0;
+const STAMINA_DRAIN: f32 = 25.0;
+const STAMINA_REGEN: f32 = 15.0;
+// Once exhausted, running stays off until stamina is back to this.
+const EXHAUSTED_RECOVERY: f32 = 30.0;
 
 #[derive(PartialEq)]
 pub enum AnimationState {
@@ -17,16 +41,9 @@ pub struct Player {
     width: f32,
     height: f32,
     speed: f32,
-    frame: usize,
-    frame_timer: f32,
-    frame_speed: f32,
+    animation: Animation,
     state: AnimationState,
-    idle_texture: Texture2D,
-    walk_texture: Texture2D,
-    run_texture: Texture2D,
     pub facing_left: bool,
-    shot_texture: Texture2D,
-    recharge_texture: Texture2D,
     is_recharging: bool,
     shots_fired: u32,
     is_shooting: bool,
@@ -35,33 +52,26 @@ pub struct Player {
     hit_timer: f32,
     health: i32,
     pub is_dead: bool,
-    death_texture: Texture2D,
     show_death_menu: bool,
-    ammo_texture: Texture2D,
     pub score: u32,
-    shoot_sound: Sound,
-    death_sound: Sound,
-    recharging_sound: Sound,
+    muzzle_timer: f32,
+    pub damage_taken: i32,
+    kill_streak: u32,
+    streak_timer: f32,
+    stamina: f32,
+    exhausted: bool,
 }
 
 impl Player {
-    pub fn new(idle_texture: Texture2D, walk_texture: Texture2D,run_texture: Texture2D,shot_texture: Texture2D,recharge_texture:Texture2D,death_texture: Texture2D,ammo_texture: Texture2D, shoot_sound:Sound, death_sound: Sound, recharging_sound: Sound) -> Self {
-        let ground_level = screen_height() * 0.8;
+    pub fn new() -> Self {
         Self {
-            x: screen_width() / 2.0,
-            y: ground_level - 50.0,
+            x: VIRTUAL_WIDTH / 2.0,
+            y: GROUND_LEVEL - 50.0,
             width: 64.0,
             height: 64.0,
-            speed: 300.0,
-            frame: 0,
-            frame_timer: 0.0,
-            frame_speed: 0.1,
+            speed: WALK_SPEED,
+            animation: Animation::new(),
             state: AnimationState::Idle,
-            idle_texture,
-            walk_texture,
-            run_texture,
-            shot_texture,
-            recharge_texture,
             facing_left : false,
             is_recharging: false,
             is_shooting: false,
@@ -71,135 +81,124 @@ impl Player {
             hit_timer: 0.0,
             health: 100,
             is_dead: false,
-            death_texture,
             show_death_menu: false,
-            ammo_texture,
             score: 0,
-            shoot_sound,
-            death_sound,
-            recharging_sound
+            muzzle_timer: 0.0,
+            damage_taken: 0,
+            kill_streak: 0,
+            streak_timer: 0.0,
+            stamina: MAX_STAMINA,
+            exhausted: false,
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
+    pub fn update(&mut self, dt: f32, assets: &Assets, mixer: &mut Mixer, puddles: &Puddles) {
+        self.muzzle_timer = (self.muzzle_timer - dt).max(0.0);
+        self.did_shoot = false;
+        self.streak_timer -= dt;
+        if self.streak_timer <= 0.0 {
+            self.kill_streak = 0;
+        }
+
         if self.is_dead {
-            self.frame_timer += dt;
-            if self.frame_timer > self.frame_speed {
-                self.frame += 1;
-                self.frame_timer = 0.0;
-
-                if self.frame >= 4 {
-                    self.show_death_menu = true; 
-                    self.frame = 3;
-                }
+            self.animation.update(&assets[assets::PLAYER_DEATH], dt);
+            if self.animation.is_finished() {
+                self.show_death_menu = true;
             }
             return;
         }
 
-        if is_key_down(KeyCode::Space) && self.shots_fired > 0 && !self.is_recharging {
-            if !self.is_shooting {
-                self.state = AnimationState::Shoting;
-                self.frame = 0;
-                self.frame_timer = 0.0;
-                self.is_shooting = true;
-            }
+        if is_key_down(KeyCode::Space) && self.shots_fired > 0 && !self.is_recharging && !self.is_shooting {
+            self.state = AnimationState::Shoting;
+            self.animation.restart();
+            self.is_shooting = true;
         }
 
         if self.is_shooting {
-            self.frame_timer += dt;
-            if self.frame_timer > self.frame_speed {
-                self.frame += 1;
-                self.frame_timer = 0.0;
-                if self.frame == 2 {
-                    play_sound(&self.shoot_sound, PlaySoundParams { looped: false, volume: 1.0 });
-                    self.did_shoot = true;
-                    self.score += 10;
-                } else {
-                    self.did_shoot = false;
-                }
-                if self.frame >= 4 {
-                    self.shots_fired -= 1;
-                    if self.shots_fired > 0 && is_key_down(KeyCode::Space) {
-                        self.frame = 0;
-                    } else {
-                        self.state = AnimationState::Idle;
-                        self.frame = 0;
-                        self.is_shooting = false;
-                    }
+            for event in self.animation.update(&assets[assets::PLAYER_SHOT], dt) {
+                self.handle(event, assets, mixer, puddles);
+            }
+            if self.animation.is_finished() {
+                self.shots_fired -= 1;
+                self.animation.restart();
+                if self.shots_fired == 0 || !is_key_down(KeyCode::Space) {
+                    self.state = AnimationState::Idle;
+                    self.is_shooting = false;
                 }
             }
+            self.update_stamina(dt);
             return;
         }
 
         if (is_key_pressed(KeyCode::R) || (is_key_down(KeyCode::Space) && self.shots_fired == 0)) && !self.is_recharging {
             self.is_recharging = true;
-            play_sound(&self.recharging_sound, PlaySoundParams { looped: false, volume: 1.5 });
+            mixer.play(&assets[assets::RECHARGING], Bus::Sfx, 1.5);
             self.state = AnimationState::Recharging;
-            self.frame = 0;
-            self.frame_timer = 0.0;
+            self.animation.restart();
         }
         
         if self.is_recharging {
-            self.frame_timer += dt;
-            if self.frame_timer > self.frame_speed {
-                self.frame += 1;
-                self.frame_timer = 0.0;
-
-                if self.frame >= 13 {
-                    self.is_recharging = false;
-                    self.shots_fired = 12;
-                    self.state = AnimationState::Idle;
-                    self.frame = 0;
-                }
+            self.animation.update(&assets[assets::PLAYER_RECHARGE], dt);
+            if self.animation.is_finished() {
+                self.is_recharging = false;
+                self.shots_fired = 12;
+                self.state = AnimationState::Idle;
+                self.animation.restart();
             }
+            self.update_stamina(dt);
             return;
         }
 
         let mut moving = false;
-        let mut running = false;
+        let running = is_key_down(KeyCode::LeftShift) && !self.exhausted;
+        self.speed = if running {
+            RUN_SPEED
+        } else if self.exhausted {
+            EXHAUSTED_SPEED
+        } else {
+            WALK_SPEED
+        };
+        let speed = self.speed * puddles.slowdown_at(self.feet());
 
         if is_key_down(KeyCode::Left) {
-            self.x -= self.speed * dt;
+            self.x -= speed * dt;
             moving = true;
             self.facing_left = true;
             self.is_recharging = false;
         }
         if is_key_down(KeyCode::Right) {
-            self.x += self.speed * dt;
+            self.x += speed * dt;
             moving = true;
             self.facing_left = false;
             self.is_recharging = false;
         }
         if is_key_down(KeyCode::Up) {
-            self.y -= self.speed * dt;
+            self.y -= speed * dt;
             moving = true;
             self.is_recharging = false;
         }
         if is_key_down(KeyCode::Down) {
-            self.y += self.speed * dt;
+            self.y += speed * dt;
             moving = true;
             self.is_recharging = false;
         }
-        if is_key_down(KeyCode::LeftShift) {
-            self.speed =500.0;
-            running = true;
-            self.is_recharging = false;
-        } else {
-            self.speed = 300.0;
-        }
 
-        self.state = if running && moving {
+        let state = if running && moving {
             AnimationState::Running
         } else if moving {
             AnimationState::Walking
         } else {
             AnimationState::Idle
         };
+        if state != self.state {
+            self.state = state;
+            self.animation.restart();
+        }
+        self.update_stamina(dt);
 
-        self.x = self.x.clamp(0.0 - self.width * 0.9, screen_width() - self.width * 1.6);
-        let ground_level = screen_height() * 0.8;
-        let max_y = screen_height() - self.height * 2.5;
-        self.y = self.y.clamp(ground_level - self.height * 2.5, max_y);
+        self.x = self.x.clamp(0.0 - self.width * 0.9, VIRTUAL_WIDTH - self.width * 1.6);
+        let max_y = VIRTUAL_HEIGHT - self.height * 2.5;
+        self.y = self.y.clamp(GROUND_LEVEL - self.height * 2.5, max_y);
 
         if self.is_hit {
             self.hit_timer += dt;
@@ -209,82 +208,130 @@ impl Player {
             }
         }
 
-        self.frame_timer += dt;
-        if self.frame_timer > self.frame_speed {
-            self.frame = (self.frame + 1) % match self.state {
-                AnimationState::Idle => 7,
-                AnimationState::Walking => 7,
-                AnimationState::Running => 8,
-                AnimationState::Shoting => 4,
-                AnimationState::Recharging => 13,
-                AnimationState::Death => 4,
-            };
-            self.frame_timer = 0.0;
+        // An exhausted walk drags its feet at the slower pace.
+        let anim_dt = if self.exhausted && moving { dt * EXHAUSTED_SPEED / WALK_SPEED } else { dt };
+        for event in self.animation.update(&assets[self.sheet()], anim_dt) {
+            self.handle(event, assets, mixer, puddles);
+        }
+    }
+
+    // Running drains stamina and anything else recovers it. Running it dry
+    // leaves the player exhausted until part of it is back.
+    fn update_stamina(&mut self, dt: f32) {
+        if self.state == AnimationState::Running {
+            self.stamina = (self.stamina - STAMINA_DRAIN * dt).max(0.0);
+            if self.stamina == 0.0 {
+                self.exhausted = true;
+            }
+        } else {
+            self.stamina = (self.stamina + STAMINA_REGEN * dt).min(MAX_STAMINA);
+            if self.stamina >= EXHAUSTED_RECOVERY {
+                self.exhausted = false;
+            }
+        }
+    }
+
+    fn handle(&mut self, event: AnimEvent, assets: &Assets, mixer: &mut Mixer, puddles: &Puddles) {
+        match event {
+            AnimEvent::Fire => {
+                mixer.play_at(&assets[assets::SHOOT], Bus::Sfx, 1.0, self.gun_position());
+                self.did_shoot = true;
+                self.muzzle_timer = MUZZLE_FLASH_TIME;
+                self.score += SHOT_POINTS;
+            }
+            AnimEvent::Footstep => puddles.footstep(self.feet(), mixer),
+            AnimEvent::Hit => {}
+        }
+    }
+
+    // Counts a kill towards the streak and returns its length.
+    pub fn add_kill(&mut self) -> u32 {
+        self.kill_streak += 1;
+        self.streak_timer = STREAK_WINDOW;
+        self.kill_streak
+    }
+
+    pub fn hit(&mut self, assets: &Assets, mixer: &mut Mixer) {
+        if !self.is_hit {
+            self.damage(10, assets, mixer);
         }
     }
 
-    pub fn hit(&mut self) {
-        if !self.is_hit && !self.is_dead {
+    pub fn damage(&mut self, amount: i32, assets: &Assets, mixer: &mut Mixer) {
+        if !self.is_dead {
             self.is_hit = true;
-            self.frame = 0;
-            self.frame_timer = 0.0;
-            self.health -= 10;
+            self.hit_timer = 0.0;
+            self.animation.restart();
+            self.health = (self.health - amount).max(0);
+            self.damage_taken += amount;
 
             if self.health <= 0 {
-                play_sound(&self.death_sound, PlaySoundParams { looped: false, volume: 1.0 });
+                mixer.play(&assets[assets::PLAYER_DEATH_SOUND], Bus::Sfx, 1.0);
+                mixer.duck(DEATH_DUCK);
                 self.die();
             }
         }
     }
 
+    pub fn health(&self) -> i32 {
+        self.health
+    }
+
+    pub fn center(&self) -> Vec2 {
+        Vec2::new(self.x + self.width * 1.25, self.y + self.height * 1.25)
+    }
+
+    pub fn feet(&self) -> Vec2 {
+        Vec2::new(self.x + self.width * 1.25, self.y + self.height * 2.3)
+    }
+
+    pub fn gun_position(&self) -> Vec2 {
+        let offset = if self.facing_left { 0.55 } else { 1.95 };
+        Vec2::new(self.x + self.width * offset, self.y + self.height * 1.4)
+    }
+
+    pub fn muzzle_flash(&self) -> f32 {
+        self.muzzle_timer / MUZZLE_FLASH_TIME
+    }
+
     fn die(&mut self) {
         if !self.is_dead {
             self.is_dead = true;
-            self.frame = 0;
-            self.frame_timer = 0.0;
+            self.animation.restart();
             self.state = AnimationState::Death;
             self.show_death_menu = false;
         }
     }
 
-    pub fn draw(&self) {
-        let (texture, frame_count) = match self.state {
-            AnimationState::Idle => (self.idle_texture.clone(), 7),
-            AnimationState::Walking => (self.walk_texture.clone(), 7),
-            AnimationState::Running => (self.run_texture.clone(), 8),
-            AnimationState::Shoting => (self.shot_texture.clone(), 4),
-            AnimationState::Recharging => (self.recharge_texture.clone(), 13),
-            AnimationState::Death => (self.death_texture.clone(), 4)
-        };
-        let frame_width = texture.width() / frame_count as f32;
-        let src = Rect::new(self.frame as f32 * frame_width, 0.0, frame_width, 128.0);
+    fn sheet(&self) -> Handle<SpriteSheet> {
+        match self.state {
+            AnimationState::Idle => assets::PLAYER_IDLE,
+            AnimationState::Walking => assets::PLAYER_WALK,
+            AnimationState::Running => assets::PLAYER_RUN,
+            AnimationState::Shoting => assets::PLAYER_SHOT,
+            AnimationState::Recharging => assets::PLAYER_RECHARGE,
+            AnimationState::Death => assets::PLAYER_DEATH,
+        }
+    }
+
+    pub fn draw(&self, assets: &Assets) {
         let color = if self.is_hit {
             RED
         } else {
             WHITE
         };
-        draw_texture_ex(
-            &texture,
+        assets[self.sheet()].draw(
+            self.animation.frame(),
             self.x,
             self.y,
+            Vec2::new(2.5 * self.width, 2.5 * self.height),
             color,
-            DrawTextureParams {
-                source: Some(src),
-                dest_size: Some(Vec2::new(2.5*self.width, 2.5*self.height)),
-                flip_x: self.facing_left,
-                ..Default::default()
-            },
+            self.facing_left,
         );
-
-        self.draw_ui();
-        
-        if self.is_dead && self.show_death_menu {
-            self.draw_death_menu();
-        }
     }
 
-    fn draw_ui(&self) {
-        let screen_w = screen_width();
+    pub fn draw_ui(&self, assets: &Assets) {
+        let screen_w = VIRTUAL_WIDTH;
         let padding = 40.0;
         let bar_width = 200.0;
         let bar_height = 30.0;
@@ -297,12 +344,17 @@ impl Player {
         let health_text_x = health_x + (bar_width - measure_text(&health_text, None, 20, 1.0).width) / 2.0;
         draw_text(&health_text, health_text_x, 40.0, 20.0, WHITE);
 
+        // Stamina bar
+        let stamina_color = if self.exhausted { Color::new(0.9, 0.4, 0.1, 1.0) } else { Color::new(0.95, 0.8, 0.2, 1.0) };
+        draw_rectangle(health_x, 56.0, bar_width, 12.0, DARKGRAY);
+        draw_rectangle(health_x + 2.0, 58.0, self.stamina * (bar_width - 4.0) / MAX_STAMINA, 8.0, stamina_color);
+
         // Ammo display
         let ammo_box_w = 100.0;
         let ammo_x = (screen_w - ammo_box_w) / 2.0;
         draw_rectangle_lines(ammo_x, 20.0, ammo_box_w, 32.0, 3.0, DARKGRAY);
         draw_texture_ex(
-            &self.ammo_texture,
+            &assets[assets::AMMO],
             ammo_x + 5.0,
             20.0,
             WHITE,
@@ -321,11 +373,15 @@ impl Player {
         draw_rectangle_lines(score_x, 20.0, score_box_w, 32.0, 3.0, DARKGRAY);
         let score_text_x = score_x + 10.0;
         draw_text(&score_text, score_text_x, 47.0, 32.0, WHITE);
+
+        if self.is_dead && self.show_death_menu {
+            self.draw_death_menu();
+        }
     }
 
     fn draw_death_menu(&self) {
-        let screen_w = screen_width();
-        let screen_h = screen_height();
+        let screen_w = VIRTUAL_WIDTH;
+        let screen_h = VIRTUAL_HEIGHT;
 
         let box_width = 300.0;
         let box_height = 150.0;
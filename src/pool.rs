@@ -0,0 +1,63 @@
+// ---------------------------
+// Pool
+// ---------------------------
+// Fixed-capacity storage for short-lived objects. Every slot is allocated up
+// front; live items are packed at the front and a dead one is swapped with
+// the last live one, so spawning and killing never touch the heap.
+pub struct Pool<T> {
+    items: Vec<T>,
+    alive: usize,
+}
+
+impl<T> Pool<T> {
+    pub fn new(capacity: usize, mut fill: impl FnMut() -> T) -> Self {
+        Self {
+            items: (0..capacity).map(|_| fill()).collect(),
+            alive: 0,
+        }
+    }
+
+    // Hands out the next free slot for the caller to reinitialise in place,
+    // or `None` when the pool is full.
+    pub fn spawn(&mut self) -> Option<&mut T> {
+        let slot = self.items.get_mut(self.alive)?;
+        self.alive += 1;
+        Some(slot)
+    }
+
+    pub fn retain(&mut self, mut keep: impl FnMut(&mut T) -> bool) {
+        let mut i = 0;
+        while i < self.alive {
+            if keep(&mut self.items[i]) {
+                i += 1;
+            } else {
+                self.alive -= 1;
+                self.items.swap(i, self.alive);
+            }
+        }
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.alive = self.alive.min(len);
+    }
+
+    pub fn clear(&mut self) {
+        self.alive = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.alive == 0
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items[..self.alive].iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.items[..self.alive].iter_mut()
+    }
+}
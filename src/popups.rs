@@ -0,0 +1,87 @@
+use macroquad::prelude::*;
+use std::fmt::{self, Write};
+
+use crate::pool::Pool;
+
+const MAX_POPUPS: usize = 64;
+const POPUP_LIFETIME: f32 = 1.0;
+
+struct Popup {
+    // Kept between uses so rewriting the text doesn't allocate.
+    text: String,
+    position: Vec2,
+    color: Color,
+    size: f32,
+    life: f32,
+}
+
+impl Popup {
+    fn empty() -> Self {
+        Self {
+            text: String::with_capacity(16),
+            position: Vec2::ZERO,
+            color: WHITE,
+            size: 0.0,
+            life: 0.0,
+        }
+    }
+
+    fn draw(&self) {
+        let t = 1.0 - self.life / POPUP_LIFETIME;
+        // Pop in slightly oversized, then settle and fade over the second half.
+        let scale = 1.0 + 0.4 * (1.0 - (t * 8.0).min(1.0));
+        let alpha = (self.life / (POPUP_LIFETIME * 0.5)).min(1.0);
+        let size = (self.size * scale) as u16;
+
+        let width = measure_text(&self.text, None, size, 1.0).width;
+        let x = self.position.x - width / 2.0;
+        let shadow = Color::new(0.0, 0.0, 0.0, alpha * 0.8);
+        draw_text(&self.text, x + 2.0, self.position.y + 2.0, size as f32, shadow);
+        draw_text(&self.text, x, self.position.y, size as f32, Color::new(self.color.r, self.color.g, self.color.b, alpha));
+    }
+}
+
+// ---------------------------
+// Popups
+// ---------------------------
+// World-space text that rises and fades, e.g. damage numbers and score.
+pub struct Popups {
+    popups: Pool<Popup>,
+}
+
+impl Popups {
+    pub fn new() -> Self {
+        Self {
+            popups: Pool::new(MAX_POPUPS, Popup::empty),
+        }
+    }
+
+    pub fn spawn(&mut self, position: Vec2, color: Color, size: f32, text: fmt::Arguments) {
+        if let Some(popup) = self.popups.spawn() {
+            popup.text.clear();
+            let _ = popup.text.write_fmt(text);
+            popup.position = position;
+            popup.color = color;
+            popup.size = size;
+            popup.life = POPUP_LIFETIME;
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.popups.retain(|p| {
+            p.life -= dt;
+            p.position.y -= 50.0 * dt;
+            p.life > 0.0
+        });
+    }
+
+    pub fn draw(&self) {
+        for popup in self.popups.iter() {
+            popup.draw();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.popups.clear();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::mixer::CHANNELS;
+
+// File name for native builds, kept next to the executable.
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_FILE: &str = "settings.cfg";
+// Local storage key for the web build.
+#[cfg(target_arch = "wasm32")]
+const SETTINGS_KEY: &str = "settings";
+
+// ---------------------------
+// Settings
+// ---------------------------
+// Player preferences that outlive a run: audio levels and whether the
+// screen shake, hit-stop and flash effects are on. Both builds store them as
+// the same `key value` lines; native in a file, the web in local storage.
+pub struct Settings {
+    // Master first, then one level per mixer bus.
+    pub levels: [f32; 4],
+    pub muted: bool,
+    pub effects: bool,
+}
+
+impl Settings {
+    fn defaults() -> Self {
+        Self {
+            levels: [1.0; 4],
+            muted: false,
+            effects: true,
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut settings = Self::defaults();
+        for line in text.lines() {
+            let mut words = line.split_whitespace();
+            let (Some(key), Some(value)) = (words.next(), words.next()) else {
+                continue;
+            };
+            if key == "muted" {
+                settings.muted = value == "true";
+            } else if key == "effects" {
+                settings.effects = value == "true";
+            } else if let Some(i) = CHANNELS.iter().position(|c| c.eq_ignore_ascii_case(key)) {
+                if let Ok(level) = value.parse::<f32>() {
+                    settings.levels[i] = level.clamp(0.0, 1.0);
+                }
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, level) in CHANNELS.iter().zip(self.levels) {
+            text += &format!("{} {}\n", name.to_lowercase(), level);
+        }
+        text += &format!("muted {}\n", self.muted);
+        text += &format!("effects {}\n", self.effects);
+        text
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Self {
+        match std::fs::read_to_string(settings_path()) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::defaults(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        // Losing the settings isn't worth interrupting the game over.
+        let _ = std::fs::write(settings_path(), self.to_text());
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Self {
+        match quad_storage::STORAGE.lock().unwrap().get(SETTINGS_KEY) {
+            Some(text) => Self::parse(&text),
+            None => Self::defaults(),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {
+        quad_storage::STORAGE.lock().unwrap().set(SETTINGS_KEY, &self.to_text());
+    }
+}
+
+// Next to the executable, so the settings follow the game rather than
+// whichever directory it was launched from.
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> std::path::PathBuf {
+    match std::env::current_exe() {
+        Ok(exe) => exe.with_file_name(SETTINGS_FILE),
+        Err(_) => SETTINGS_FILE.into(),
+    }
+}
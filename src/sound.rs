@@ -0,0 +1,231 @@
+use macroquad::prelude::*;
+use macroquad::audio::*;
+
+// Macroquad plays every sound centered with no pan control. To position a
+// sound we split a 16-bit PCM wav into a left-only and a right-only copy and
+// balance the volume of the two.
+
+const UNSUPPORTED_WAV: &str = "unsupported wav format, expected 16-bit PCM";
+
+struct Wav {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+fn parse_wav(bytes: &[u8]) -> Option<Wav> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().ok()?) as usize;
+        let body = bytes.get(pos + 8..pos + 8 + size)?;
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let audio_format = u16::from_le_bytes([body[0], body[1]]);
+                let channels = u16::from_le_bytes([body[2], body[3]]);
+                let sample_rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]);
+                let bits = u16::from_le_bytes([body[14], body[15]]);
+                if audio_format != 1 || bits != 16 || !(1..=2).contains(&channels) {
+                    return None;
+                }
+                format = Some((channels, sample_rate));
+            }
+            b"data" => data = Some(body),
+            _ => {}
+        }
+
+        // Chunks are padded to an even size.
+        pos += 8 + size + (size & 1);
+    }
+
+    let (channels, sample_rate) = format?;
+    let samples: Vec<i16> = data?
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+    if samples.len() < channels as usize {
+        return None;
+    }
+
+    Some(Wav { channels, sample_rate, samples })
+}
+
+pub fn encode_wav(channels: u16, sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let block_align = channels * 2;
+
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&channels.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+// Playing the samples back at a scaled rate shifts the pitch (and length)
+// by the same factor.
+fn split_channels(wav: &Wav, pitch: f32) -> (Vec<u8>, Vec<u8>) {
+    let frames = wav.samples.len() / wav.channels as usize;
+    let mut left = Vec::with_capacity(frames * 2);
+    let mut right = Vec::with_capacity(frames * 2);
+
+    for frame in wav.samples.chunks_exact(wav.channels as usize) {
+        let mono = (frame.iter().map(|&s| s as i32).sum::<i32>() / frame.len() as i32) as i16;
+        left.extend_from_slice(&[mono, 0]);
+        right.extend_from_slice(&[0, mono]);
+    }
+
+    let sample_rate = (wav.sample_rate as f32 * pitch) as u32;
+    (encode_wav(2, sample_rate, &left), encode_wav(2, sample_rate, &right))
+}
+
+// Balance law: full volume on both sides at center, fading one side out
+// towards the edges.
+fn pan_volumes(volume: f32, pan: f32) -> (f32, f32) {
+    let pan = pan.clamp(-1.0, 1.0);
+    (volume * (1.0 - pan).min(1.0), volume * (1.0 + pan).min(1.0))
+}
+
+// quad-snd panics on a wav it can't decode rather than returning an error, so
+// the file is checked here and quad-snd gets a freshly written copy.
+pub async fn load_wav(bytes: &[u8]) -> Result<Sound, macroquad::Error> {
+    let wav = parse_wav(bytes).ok_or(UNSUPPORTED_WAV)?;
+    load_sound_from_bytes(&encode_wav(wav.channels, wav.sample_rate, &wav.samples)).await
+}
+
+// A short burst of decaying, low-passed noise; close enough to a foot
+// landing in water that we don't need another asset for it.
+pub async fn splash_sound() -> Result<Sound, macroquad::Error> {
+    let sample_rate = 22050;
+    let len = sample_rate as usize * 15 / 100;
+    let mut last = 0.0;
+    let samples: Vec<i16> = (0..len)
+        .map(|i| {
+            let t = i as f32 / len as f32;
+            last += (rand::gen_range(-1.0, 1.0) - last) * 0.3;
+            (last * (1.0 - t).powi(3) * 20000.0) as i16
+        })
+        .collect();
+
+    load_sound_from_bytes(&encode_wav(1, sample_rate, &samples)).await
+}
+
+// ---------------------------
+// StereoSound
+// ---------------------------
+// How a stereo sound is loaded and played back: its pitch, how far one-shots
+// may stray from that pitch at random, and how many copies may be heard at
+// once.
+#[derive(Clone, Copy)]
+pub struct Playback {
+    pub pitch: f32,
+    pub variation: f32,
+    pub voices: usize,
+}
+
+// One pitch of a sound. quad-snd can't change pitch on the fly, so every
+// variant is decoded up front.
+#[derive(Clone)]
+pub struct Variant {
+    left: Sound,
+    right: Sound,
+    pub duration: f32,
+}
+
+impl Variant {
+    pub fn play(&self, volume: f32, pan: f32) {
+        self.play_ex(false, volume, pan);
+    }
+
+    fn play_ex(&self, looped: bool, volume: f32, pan: f32) {
+        let (left, right) = pan_volumes(volume, pan);
+        play_sound(&self.left, PlaySoundParams { looped, volume: left });
+        play_sound(&self.right, PlaySoundParams { looped, volume: right });
+    }
+}
+
+#[derive(Clone)]
+pub struct StereoSound {
+    // Identifies the sound to the voice manager. It's the manifest handle
+    // index, so it's shared by clones and survives a hot reload.
+    key: usize,
+    max_voices: usize,
+    // The first variant is at the exact pitch; loops always use it.
+    variants: Vec<Variant>,
+}
+
+impl StereoSound {
+    pub async fn from_bytes(bytes: &[u8], playback: Playback, key: usize) -> Result<Self, macroquad::Error> {
+        let wav = parse_wav(bytes).ok_or(UNSUPPORTED_WAV)?;
+        let frames = wav.samples.len() / wav.channels as usize;
+
+        let mut pitches = vec![playback.pitch];
+        if playback.variation > 0.0 {
+            pitches.push(playback.pitch * (1.0 - playback.variation));
+            pitches.push(playback.pitch * (1.0 + playback.variation));
+        }
+        let mut variants = Vec::with_capacity(pitches.len());
+        for pitch in pitches {
+            let (left, right) = split_channels(&wav, pitch);
+            variants.push(Variant {
+                left: load_sound_from_bytes(&left).await?,
+                right: load_sound_from_bytes(&right).await?,
+                duration: frames as f32 / (wav.sample_rate as f32 * pitch),
+            });
+        }
+
+        Ok(Self {
+            key,
+            max_voices: playback.voices,
+            variants,
+        })
+    }
+
+    pub fn key(&self) -> usize {
+        self.key
+    }
+
+    pub fn max_voices(&self) -> usize {
+        self.max_voices
+    }
+
+    // Picks a pitch at random so repeats don't sound identical.
+    pub fn variant(&self) -> &Variant {
+        &self.variants[rand::gen_range(0, self.variants.len())]
+    }
+
+    pub fn play_looped(&self, volume: f32, pan: f32) {
+        self.variants[0].play_ex(true, volume, pan);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stop(&self) {
+        stop_sound(&self.variants[0].left);
+        stop_sound(&self.variants[0].right);
+    }
+
+    pub fn set_volume(&self, volume: f32, pan: f32) {
+        let (left, right) = pan_volumes(volume, pan);
+        set_sound_volume(&self.variants[0].left, left);
+        set_sound_volume(&self.variants[0].right, right);
+    }
+}
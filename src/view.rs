@@ -0,0 +1,90 @@
+use macroquad::prelude::*;
+use macroquad::miniquad::{BlendFactor, BlendState, Equation};
+
+use crate::lighting::blend_material;
+
+pub const VIRTUAL_WIDTH: f32 = 1280.0;
+pub const VIRTUAL_HEIGHT: f32 = 720.0;
+pub const GROUND_LEVEL: f32 = VIRTUAL_HEIGHT * 0.8;
+
+// Widest split between the colour channels during a chromatic flash, in pixels.
+const MAX_CHROMA_OFFSET: f32 = 8.0;
+
+// ---------------------------
+// View
+// ---------------------------
+pub struct View {
+    target: RenderTarget,
+    camera: Camera2D,
+    ui_camera: Camera2D,
+    additive: Material,
+}
+
+impl View {
+    pub fn new() -> Self {
+        let target = render_target(VIRTUAL_WIDTH as u32, VIRTUAL_HEIGHT as u32);
+        target.texture.set_filter(FilterMode::Linear);
+
+        // Positive y zoom: render targets are stored bottom-up, so this keeps
+        // virtual y pointing down once the texture is drawn to the screen.
+        let camera = || Camera2D {
+            target: vec2(VIRTUAL_WIDTH / 2.0, VIRTUAL_HEIGHT / 2.0),
+            zoom: vec2(2.0 / VIRTUAL_WIDTH, 2.0 / VIRTUAL_HEIGHT),
+            render_target: Some(target.clone()),
+            ..Default::default()
+        };
+
+        Self {
+            camera: camera(),
+            ui_camera: camera(),
+            target,
+            additive: blend_material(BlendState::new(Equation::Add, BlendFactor::One, BlendFactor::One)),
+        }
+    }
+
+    // Moves the world camera, e.g. for screen shake. The HUD stays put.
+    pub fn set_offset(&mut self, offset: Vec2) {
+        self.camera.target = vec2(VIRTUAL_WIDTH / 2.0, VIRTUAL_HEIGHT / 2.0) + offset;
+    }
+
+    pub fn begin(&self) {
+        set_camera(&self.camera);
+    }
+
+    pub fn begin_ui(&self) {
+        set_camera(&self.ui_camera);
+    }
+
+    pub fn present(&self, chroma: f32) {
+        set_default_camera();
+        clear_background(BLACK);
+
+        let scale = (screen_width() / VIRTUAL_WIDTH).min(screen_height() / VIRTUAL_HEIGHT);
+        let width = VIRTUAL_WIDTH * scale;
+        let height = VIRTUAL_HEIGHT * scale;
+        let x = (screen_width() - width) / 2.0;
+        let y = (screen_height() - height) / 2.0;
+        let params = DrawTextureParams {
+            dest_size: Some(Vec2::new(width, height)),
+            ..Default::default()
+        };
+
+        if chroma <= 0.0 {
+            draw_texture_ex(&self.target.texture, x, y, WHITE, params);
+            return;
+        }
+
+        // Add the red, green and blue channels back together with red and
+        // blue pulled apart.
+        let offset = chroma * MAX_CHROMA_OFFSET * scale;
+        gl_use_material(&self.additive);
+        draw_texture_ex(&self.target.texture, x - offset, y, RED_CHANNEL, params.clone());
+        draw_texture_ex(&self.target.texture, x, y, GREEN_CHANNEL, params.clone());
+        draw_texture_ex(&self.target.texture, x + offset, y, BLUE_CHANNEL, params);
+        gl_use_default_material();
+    }
+}
+
+const RED_CHANNEL: Color = Color::new(1.0, 0.0, 0.0, 1.0);
+const GREEN_CHANNEL: Color = Color::new(0.0, 1.0, 0.0, 1.0);
+const BLUE_CHANNEL: Color = Color::new(0.0, 0.0, 1.0, 1.0);
@@ -0,0 +1,77 @@
+use macroquad::prelude::*;
+
+use crate::sound::{StereoSound, Variant};
+
+// One-shots are played at a random volume down to this fraction of the
+// requested one.
+const VOLUME_JITTER: f32 = 0.15;
+// Requests quieter than this aren't worth a voice.
+const INAUDIBLE: f32 = 0.01;
+
+struct Request {
+    sound: usize,
+    max_voices: usize,
+    variant: Variant,
+    volume: f32,
+    pan: f32,
+}
+
+struct Voice {
+    sound: usize,
+    ends: f64,
+}
+
+// ---------------------------
+// VoiceManager
+// ---------------------------
+// Collects the one-shots requested during a frame and plays them together,
+// loudest first, so a crowd of zombies attacking at once fills each sound's
+// voice limit with the nearest ones and drops the rest. quad-snd can't stop
+// a single instance of a sound, so voices are tracked by their expected end
+// time rather than stolen.
+pub struct VoiceManager {
+    pending: Vec<Request>,
+    playing: Vec<Voice>,
+}
+
+impl VoiceManager {
+    pub fn new() -> Self {
+        Self {
+            pending: vec![],
+            playing: vec![],
+        }
+    }
+
+    pub fn request(&mut self, sound: &StereoSound, volume: f32, pan: f32) {
+        if volume < INAUDIBLE {
+            return;
+        }
+        self.pending.push(Request {
+            sound: sound.key(),
+            max_voices: sound.max_voices(),
+            variant: sound.variant().clone(),
+            volume,
+            pan,
+        });
+    }
+
+    pub fn flush(&mut self) {
+        let now = get_time();
+        self.playing.retain(|voice| voice.ends > now);
+
+        // Sorted before jitter is applied, so nearer always wins.
+        self.pending.sort_by(|a, b| b.volume.total_cmp(&a.volume));
+        for request in self.pending.drain(..) {
+            let voices = self.playing.iter().filter(|voice| voice.sound == request.sound).count();
+            if voices >= request.max_voices {
+                continue;
+            }
+            let jitter = rand::gen_range(1.0 - VOLUME_JITTER, 1.0);
+            request.variant.play(request.volume * jitter, request.pan);
+            self.playing.push(Voice {
+                sound: request.sound,
+                ends: now + request.variant.duration as f64,
+            });
+        }
+    }
+}
@@ -1,89 +1,153 @@
 use macroquad::prelude::*;
 use macroquad::audio::*;
 
+use crate::batch::Batch;
+use crate::lighting::Lighting;
+use crate::particles::ParticleSystem;
+use crate::pool::Pool;
+use crate::mixer::{Bus, Mixer};
+use crate::sound::StereoSound;
+use crate::view::{GROUND_LEVEL, VIRTUAL_HEIGHT, VIRTUAL_WIDTH};
+
 // ---------------------------
 // Raindrop
 // ---------------------------
+pub const MAX_RAINDROPS: usize = 4096;
+
 pub struct Raindrop {
-    pub x: f32,
-    pub y: f32,
+    x: f32,
+    y: f32,
     speed: f32,
-    pub length: f32,
+    length: f32,
+    slant: f32,
 }
 
 impl Raindrop {
     pub fn new() -> Self {
-        Self {
-            x: rand::gen_range(0., screen_width()),
-            y: rand::gen_range(-500., 0.),
-            speed: rand::gen_range(300., 600.),
-            length: rand::gen_range(10., 20.),
-        }
+        let mut drop = Self {
+            x: 0.0,
+            y: 0.0,
+            speed: 0.0,
+            length: 0.0,
+            slant: 0.0,
+        };
+        drop.respawn();
+        drop
     }
 
-    pub fn update(&mut self, dt: f32) {
+    pub fn respawn(&mut self) {
+        self.x = rand::gen_range(0., VIRTUAL_WIDTH);
+        self.y = rand::gen_range(-500., 0.);
+        self.speed = rand::gen_range(300., 600.);
+        self.length = rand::gen_range(10., 20.);
+    }
+
+    pub fn update(&mut self, dt: f32, wind: f32) {
         self.y += self.speed * dt;
+        self.x += wind * dt;
+        self.slant = wind / self.speed * self.length;
+
+        // Drops blown off one side come back in on the other.
+        if self.x < -50.0 {
+            self.x += VIRTUAL_WIDTH + 100.0;
+        } else if self.x > VIRTUAL_WIDTH + 50.0 {
+            self.x -= VIRTUAL_WIDTH + 100.0;
+        }
     }
 
-    pub fn draw(&self, brightness: f32) {
+    pub fn draw(&self, brightness: f32, batch: &mut Batch) {
         let c = 0.5 + brightness * 0.5;
-        draw_line(self.x, self.y, self.x, self.y + self.length, 2.0, Color::new(c, c, c, 1.0));
+        batch.line(self.x, self.y, self.x + self.slant, self.y + self.length, 2.0, Color::new(c, c, c, 1.0));
+    }
+
+    pub fn is_near_ground(&self, splash_chance: f32) -> bool {
+        self.y + self.length >= GROUND_LEVEL && rand::gen_range(0.0, 1.0) < splash_chance
     }
 
-    pub fn is_near_ground(&self) -> bool {
-        let ground_level = screen_height() * 0.8;
-        self.y + self.length >= ground_level && rand::gen_range(0.0, 1.0) < 0.1
+    pub fn is_off_screen(&self) -> bool {
+        self.y > VIRTUAL_HEIGHT
     }
 }
 
 // ---------------------------
-// Splash
+// Rain
 // ---------------------------
-pub struct Splash {
-    x: f32,
-    y: f32,
-    radius: f32,
-    lifetime: f32,
+pub struct Rain {
+    drops: Pool<Raindrop>,
+    batch: Batch,
 }
 
-impl Splash {
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y, radius: 2.0, lifetime: 0.5 }
+impl Rain {
+    pub fn new() -> Self {
+        Self {
+            drops: Pool::new(MAX_RAINDROPS, Raindrop::new),
+            batch: Batch::new(),
+        }
     }
 
-    pub fn update(&mut self, dt: f32) {
-        self.radius += 20.0 * dt;
-        self.lifetime -= dt;
-    }
+    pub fn update(&mut self, dt: f32, count: usize, wind: f32, splash_chance: f32, particles: &mut ParticleSystem) {
+        self.drops.truncate(count);
+        while self.drops.len() < count {
+            match self.drops.spawn() {
+                Some(drop) => drop.respawn(),
+                None => break,
+            }
+        }
 
-    pub fn draw(&self, brightness: f32) {
-        let alpha = self.lifetime.clamp(0.0, 0.5) / 0.5;
-        let b = 1.0 + brightness * 0.5;
-        draw_line(self.x, self.y, self.x + self.radius, self.y, 2.0, Color::new(b, b, b, alpha));
+        for drop in self.drops.iter_mut() {
+            drop.update(dt, wind);
+            if drop.is_near_ground(splash_chance) {
+                particles.splash(Vec2::new(drop.x + drop.slant, drop.y + drop.length), wind);
+                drop.respawn();
+            } else if drop.is_off_screen() {
+                drop.respawn();
+            }
+        }
     }
 
-    pub fn is_dead(&self) -> bool {
-        self.lifetime <= 0.0
+    pub fn draw(&mut self, brightness: f32) {
+        for drop in self.drops.iter() {
+            drop.draw(brightness, &mut self.batch);
+        }
+        self.batch.flush();
     }
 }
 
 // ---------------------------
 // LightningBolt
 // ---------------------------
+// Worst case is a sky bolt: up to 58 short steps through the top 40% of the
+// screen and 11 long ones below, plus the start and overshoot points.
+const BOLT_POINTS: usize = 128;
+
 pub struct LightningBolt {
     points: Vec<(f32, f32)>,
+    // Per-segment brightness, rolled once when the bolt is generated.
+    shades: Vec<f32>,
     lifetime: f32,
+    intensity: f32,
 }
 
+// Bolts live in pools and are regenerated in place, reusing `points`.
 impl LightningBolt {
     pub fn new() -> Self {
-        let mut points = Vec::new();
-        let mut x = rand::gen_range(screen_width() * 0.2, screen_width() * 0.8);
+        Self {
+            points: Vec::with_capacity(BOLT_POINTS),
+            shades: Vec::with_capacity(BOLT_POINTS),
+            lifetime: 0.0,
+            intensity: 0.0,
+        }
+    }
+
+    pub fn sky(&mut self, distance: f32) {
+        let points = &mut self.points;
+        points.clear();
+        let mut x = rand::gen_range(VIRTUAL_WIDTH * 0.2, VIRTUAL_WIDTH * 0.8);
         let mut y = 0.0;
         points.push((x, y));
 
-        while y < screen_height() * 0.7 {
-            let (dx, dy) = if y < screen_height() * 0.4 {
+        while y < VIRTUAL_HEIGHT * 0.7 {
+            let (dx, dy) = if y < VIRTUAL_HEIGHT * 0.4 {
                 // Top 40%: mostly horizontal
                 (
                     rand::gen_range(-50.0, 50.0),
@@ -96,14 +160,40 @@ impl LightningBolt {
                     rand::gen_range(20.0, 40.0),
                 )
             };
-            x = (x + dx).clamp(0.0, screen_width());
+            x = (x + dx).clamp(0.0, VIRTUAL_WIDTH);
             y += dy;
             points.push((x, y));
         }
 
-        Self {
-            points,
-            lifetime: 0.15,
+        self.lifetime = 0.15;
+        self.intensity = 1.0 - distance * 0.8;
+        self.roll_shades();
+    }
+
+    pub fn strike(&mut self, target: Vec2) {
+        let top_x = (target.x + rand::gen_range(-150.0, 150.0)).clamp(0.0, VIRTUAL_WIDTH);
+        let points = &mut self.points;
+        points.clear();
+        points.push((top_x, 0.0));
+        let mut y = 0.0;
+
+        while y < target.y - 40.0 {
+            y += rand::gen_range(20.0, 40.0);
+            let t = y / target.y;
+            let x = top_x + (target.x - top_x) * t + rand::gen_range(-20.0, 20.0);
+            points.push((x, y));
+        }
+        points.push((target.x, target.y));
+
+        self.lifetime = 0.3;
+        self.intensity = 1.0;
+        self.roll_shades();
+    }
+
+    fn roll_shades(&mut self) {
+        self.shades.clear();
+        for _ in 1..self.points.len() {
+            self.shades.push(rand::gen_range(0.8, 1.0));
         }
     }
 
@@ -111,18 +201,18 @@ impl LightningBolt {
         self.lifetime -= dt;
     }
 
-    pub fn draw(&self) {
-        let alpha = self.lifetime.clamp(0.0, 0.15) / 0.15;
+    pub fn draw(&self, thickness: f32) {
+        let alpha = self.lifetime.clamp(0.0, 0.15) / 0.15 * self.intensity;
         for i in 0..self.points.len() - 1 {
             let (x1, y1) = self.points[i];
             let (x2, y2) = self.points[i + 1];
-            let brightness = rand::gen_range(0.8, 1.0);
+            let brightness = self.shades[i];
             draw_line(
                 x1,
                 y1,
                 x2,
                 y2,
-                2.0,
+                thickness,
                 Color::new(brightness, brightness, brightness, alpha),
             );
         }
@@ -133,38 +223,153 @@ impl LightningBolt {
     }
 }
 
+// ---------------------------
+// Strike
+// ---------------------------
+pub const STRIKE_RADIUS: f32 = 90.0;
+const STRIKE_WARNING: f32 = 0.8;
+const STRIKE_CHANCE: f32 = 0.35;
+const MAX_STRIKES: usize = 4;
+const SCORCH_LIFETIME: f32 = 20.0;
+// Seconds of thunder delay for the farthest bolts, roughly a kilometre.
+const MAX_THUNDER_DELAY: f32 = 3.0;
+// Seconds a thunderclap at full volume holds the rain down for.
+const THUNDER_DUCK: f32 = 2.0;
+
+// A ground strike glows for a moment before the bolt lands, giving
+// everything standing there a chance to get out of the way.
+struct Strike {
+    x: f32,
+    y: f32,
+    warning: f32,
+}
+
+impl Strike {
+    fn new() -> Self {
+        Self {
+            x: rand::gen_range(60.0, VIRTUAL_WIDTH - 60.0),
+            y: rand::gen_range(GROUND_LEVEL + 20.0, VIRTUAL_HEIGHT - 20.0),
+            warning: STRIKE_WARNING,
+        }
+    }
+
+    fn empty() -> Self {
+        Self { x: 0.0, y: 0.0, warning: 0.0 }
+    }
+
+    fn charge(&self) -> f32 {
+        1.0 - self.warning / STRIKE_WARNING
+    }
+
+    fn draw(&self) {
+        let pulse = 0.6 + 0.4 * (get_time() as f32 * 30.0).sin();
+        let alpha = self.charge() * pulse * 0.6;
+        draw_ellipse(self.x, self.y, STRIKE_RADIUS, STRIKE_RADIUS * 0.3, 0.0, Color::new(0.5, 0.6, 1.0, alpha * 0.4));
+        draw_ellipse(self.x, self.y, STRIKE_RADIUS * 0.3, STRIKE_RADIUS * 0.1, 0.0, Color::new(0.8, 0.9, 1.0, alpha));
+    }
+}
+
+struct ScorchMark {
+    x: f32,
+    y: f32,
+    lifetime: f32,
+}
+
+impl ScorchMark {
+    fn empty() -> Self {
+        Self { x: 0.0, y: 0.0, lifetime: 0.0 }
+    }
+
+    fn draw(&self) {
+        let alpha = (self.lifetime / SCORCH_LIFETIME).clamp(0.0, 1.0);
+        draw_ellipse(self.x, self.y, STRIKE_RADIUS * 0.7, STRIKE_RADIUS * 0.2, 0.0, Color::new(0.02, 0.02, 0.02, alpha * 0.8));
+        draw_ellipse(self.x, self.y, STRIKE_RADIUS * 0.35, STRIKE_RADIUS * 0.1, 0.0, Color::new(0.0, 0.0, 0.0, alpha));
+    }
+}
+
+struct Thunder {
+    delay: f32,
+    volume: f32,
+    pan: f32,
+}
+
+impl Thunder {
+    // Far bolts rumble late and quietly, close ones crack right away.
+    fn new(x: f32, distance: f32) -> Self {
+        Self {
+            delay: distance * MAX_THUNDER_DELAY,
+            volume: 1.0 - distance * 0.75,
+            pan: (x / VIRTUAL_WIDTH * 2.0 - 1.0) * (1.0 - distance * 0.5),
+        }
+    }
+
+    fn empty() -> Self {
+        Self { delay: 0.0, volume: 0.0, pan: 0.0 }
+    }
+}
+
 // ---------------------------
 // LightningManager
 // ---------------------------
 pub struct LightningManager {
-    bolts: Vec<LightningBolt>,
+    bolts: Pool<LightningBolt>,
+    strike_bolts: Pool<LightningBolt>,
+    strikes: Pool<Strike>,
+    scorches: Pool<ScorchMark>,
+    impacts: Vec<Vec2>,
+    thunders: Pool<Thunder>,
     timer: f32,
     cooldown: f32,
     flashes: u8,
     flash_timer: f32,
-    thunder_sound: Sound,
+    flash_distance: f32,
+    thunder_sound: StereoSound,
 }
 
 impl LightningManager {
-    pub fn new(thunder_sound: Sound) -> Self {
+    pub fn new(thunder_sound: StereoSound) -> Self {
         Self {
-            bolts: vec![],
+            bolts: Pool::new(8, LightningBolt::new),
+            strike_bolts: Pool::new(MAX_STRIKES, LightningBolt::new),
+            strikes: Pool::new(MAX_STRIKES, Strike::empty),
+            scorches: Pool::new(16, ScorchMark::empty),
+            impacts: Vec::with_capacity(MAX_STRIKES),
+            thunders: Pool::new(8, Thunder::empty),
             timer: 0.0,
             cooldown: rand::gen_range(13.0, 20.0),
             flashes: 0,
             flash_timer: 0.0,
+            flash_distance: 0.0,
             thunder_sound,
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
-        self.timer += dt;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_thunder_sound(&mut self, thunder_sound: StereoSound) {
+        self.thunder_sound = thunder_sound;
+    }
+
+    pub fn update(&mut self, dt: f32, frequency: f32, mixer: &mut Mixer) {
+        self.timer += dt * frequency;
+        self.impacts.clear();
 
         if self.timer > self.cooldown && self.flashes == 0 {
-            self.bolts.push(LightningBolt::new());
-            play_sound(&self.thunder_sound, PlaySoundParams { looped: false, volume: 1.0 });
-            self.flashes = rand::gen_range(2, 4);
-            self.flash_timer = 0.1;
+            if rand::gen_range(0.0, 1.0) < STRIKE_CHANCE {
+                if let Some(strike) = self.strikes.spawn() {
+                    *strike = Strike::new();
+                }
+            } else {
+                self.flash_distance = rand::gen_range(0.1, 1.0);
+                if let Some(bolt) = self.bolts.spawn() {
+                    bolt.sky(self.flash_distance);
+                    let x = bolt.points[0].0;
+                    if let Some(thunder) = self.thunders.spawn() {
+                        *thunder = Thunder::new(x, self.flash_distance);
+                    }
+                }
+                self.flashes = rand::gen_range(2, 4);
+                self.flash_timer = 0.1;
+            }
             self.timer = 0.0;
         }
 
@@ -173,17 +378,57 @@ impl LightningManager {
             if self.flash_timer <= 0.0 {
                 self.flashes -= 1;
                 if self.flashes > 0 {
-                    self.bolts.push(LightningBolt::new());
+                    if let Some(bolt) = self.bolts.spawn() {
+                        bolt.sky(self.flash_distance);
+                    }
                     self.flash_timer = rand::gen_range(0.05, 0.15);
                 }
             }
         }
 
-        for bolt in self.bolts.iter_mut() {
-            bolt.update(dt);
-        }
+        self.strikes.retain(|strike| {
+            strike.warning -= dt;
+            if strike.warning > 0.0 {
+                return true;
+            }
 
-        self.bolts.retain(|b| !b.is_dead());
+            let target = Vec2::new(strike.x, strike.y);
+            if let Some(bolt) = self.strike_bolts.spawn() {
+                bolt.strike(target);
+            }
+            if let Some(scorch) = self.scorches.spawn() {
+                *scorch = ScorchMark { x: strike.x, y: strike.y, lifetime: SCORCH_LIFETIME };
+            }
+            if let Some(thunder) = self.thunders.spawn() {
+                *thunder = Thunder::new(strike.x, 0.0);
+            }
+            self.impacts.push(target);
+            false
+        });
+
+        self.thunders.retain(|thunder| {
+            thunder.delay -= dt;
+            if thunder.delay <= 0.0 {
+                mixer.play_stereo(&self.thunder_sound, Bus::Ambience, thunder.volume, thunder.pan);
+                mixer.duck(thunder.volume * THUNDER_DUCK);
+                return false;
+            }
+            true
+        });
+
+        self.bolts.retain(|b| {
+            b.update(dt);
+            !b.is_dead()
+        });
+        self.strike_bolts.retain(|b| {
+            b.update(dt);
+            !b.is_dead()
+        });
+
+        self.scorches.retain(|s| {
+            s.lifetime -= dt;
+            s.lifetime > 0.0
+        });
 
         if self.bolts.is_empty() && self.flashes == 0 {
             self.cooldown = rand::gen_range(4.0, 8.0);
@@ -191,12 +436,503 @@ impl LightningManager {
     }
 
     pub fn draw(&self) {
-        for bolt in &self.bolts {
-            bolt.draw();
+        for bolt in self.bolts.iter() {
+            bolt.draw(2.0);
+        }
+    }
+
+    pub fn draw_ground(&self) {
+        for scorch in self.scorches.iter() {
+            scorch.draw();
+        }
+        for strike in self.strikes.iter() {
+            strike.draw();
+        }
+    }
+
+    pub fn draw_strikes(&self) {
+        for bolt in self.strike_bolts.iter() {
+            bolt.draw(4.0);
+        }
+    }
+
+    pub fn draw_lights(&self, lighting: &mut Lighting) {
+        for strike in self.strikes.iter() {
+            let c = strike.charge() * 0.6;
+            lighting.point(Vec2::new(strike.x, strike.y), STRIKE_RADIUS * 1.5, Color::new(c * 0.7, c * 0.8, c, 1.0));
+        }
+    }
+
+    // Ground points hit by lightning during the last update.
+    pub fn impacts(&self) -> &[Vec2] {
+        &self.impacts
+    }
+
+    // How strongly the current flash lights up the scene, 0 when dark.
+    pub fn brightness(&self) -> f32 {
+        self.bolts
+            .iter()
+            .chain(self.strike_bolts.iter())
+            .map(|b| b.intensity)
+            .fold(0.0, f32::max)
+    }
+}
+
+// ---------------------------
+// Wind
+// ---------------------------
+pub struct Wind {
+    pub velocity: f32,
+    direction: f32,
+    gust: f32,
+    gust_target: f32,
+    gust_timer: f32,
+}
+
+impl Wind {
+    pub fn new() -> Self {
+        Self {
+            velocity: 0.0,
+            direction: 1.0,
+            gust: 0.0,
+            gust_target: 0.0,
+            gust_timer: 0.0,
         }
     }
 
-    pub fn is_flashing(&self) -> bool {
-        !self.bolts.is_empty()
+    pub fn update(&mut self, dt: f32, strength: f32) {
+        self.gust_timer -= dt;
+        if self.gust_timer <= 0.0 {
+            self.gust_target = rand::gen_range(-0.3, 0.6);
+            self.gust_timer = rand::gen_range(1.5, 4.0);
+            if rand::gen_range(0.0, 1.0) < 0.05 {
+                self.direction = -self.direction;
+            }
+        }
+
+        self.gust += (self.gust_target - self.gust) * (1.0 - (-dt * 1.5).exp());
+        let target = self.direction * strength * (0.7 + self.gust);
+        self.velocity += (target - self.velocity) * (1.0 - (-dt * 2.0).exp());
+    }
+}
+
+// ---------------------------
+// Fog
+// ---------------------------
+const FOG_TEXTURE_WIDTH: usize = 256;
+const FOG_TEXTURE_HEIGHT: usize = 64;
+
+// Tileable value noise, so a layer can scroll forever by wrapping its offset.
+fn fog_texture(cells_x: usize, cells_y: usize) -> Texture2D {
+    let grid: Vec<f32> = (0..cells_x * (cells_y + 1)).map(|_| rand::gen_range(0.0, 1.0)).collect();
+    let at = |gx: usize, gy: usize| grid[gy * cells_x + gx % cells_x];
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+
+    let mut image = Image::gen_image_color(FOG_TEXTURE_WIDTH as u16, FOG_TEXTURE_HEIGHT as u16, BLANK);
+    for py in 0..FOG_TEXTURE_HEIGHT {
+        for px in 0..FOG_TEXTURE_WIDTH {
+            let fx = px as f32 / FOG_TEXTURE_WIDTH as f32 * cells_x as f32;
+            let fy = py as f32 / FOG_TEXTURE_HEIGHT as f32 * cells_y as f32;
+            let (gx, gy) = (fx as usize, fy as usize);
+            let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+
+            let top = at(gx, gy) + (at(gx + 1, gy) - at(gx, gy)) * tx;
+            let bottom = at(gx, gy + 1) + (at(gx + 1, gy + 1) - at(gx, gy + 1)) * tx;
+            let noise = top + (bottom - top) * ty;
+
+            // Fade out towards the top and bottom edges so the band has no hard lines.
+            let v = py as f32 / FOG_TEXTURE_HEIGHT as f32;
+            let edge = (v * (1.0 - v) * 4.0).clamp(0.0, 1.0);
+            image.set_pixel(px as u32, py as u32, Color::new(1.0, 1.0, 1.0, noise * edge));
+        }
     }
-}
\ No newline at end of file
+
+    let texture = Texture2D::from_image(&image);
+    texture.set_filter(FilterMode::Linear);
+    texture
+}
+
+struct FogLayer {
+    texture: Texture2D,
+    offset: f32,
+    drift: f32,
+    y: f32,
+    height: f32,
+    alpha: f32,
+}
+
+pub struct Fog {
+    layers: Vec<FogLayer>,
+    density: f32,
+    clearing: f32,
+}
+
+impl Fog {
+    pub fn new() -> Self {
+        let layers = vec![
+            FogLayer { texture: fog_texture(4, 2), offset: 0.0, drift: 0.3, y: GROUND_LEVEL - 90.0, height: 180.0, alpha: 0.5 },
+            FogLayer { texture: fog_texture(6, 3), offset: 0.0, drift: 0.5, y: GROUND_LEVEL - 20.0, height: 160.0, alpha: 0.6 },
+            FogLayer { texture: fog_texture(8, 3), offset: 0.0, drift: 0.8, y: GROUND_LEVEL + 40.0, height: 120.0, alpha: 0.5 },
+        ];
+
+        Self {
+            layers,
+            density: 0.0,
+            clearing: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, wind: f32, density: f32, brightness: f32) {
+        for layer in self.layers.iter_mut() {
+            layer.offset = (layer.offset + (wind * layer.drift + 8.0) * dt).rem_euclid(VIRTUAL_WIDTH);
+        }
+
+        self.density += (density - self.density) * (1.0 - (-dt / 3.0).exp());
+
+        // A lightning flash burns the fog away for a moment.
+        if brightness > 0.5 {
+            self.clearing = 1.0;
+        }
+        self.clearing = (self.clearing - dt * 0.5).max(0.0);
+    }
+
+    fn effective_density(&self) -> f32 {
+        self.density * (1.0 - self.clearing * 0.8)
+    }
+
+    // How visible something is at `distance` from the viewer, from 1 (clear) down.
+    pub fn visibility(&self, distance: f32) -> f32 {
+        let t = ((distance - 150.0) / 450.0).clamp(0.0, 1.0);
+        1.0 - self.effective_density() * t * t * (3.0 - 2.0 * t) * 0.85
+    }
+
+    pub fn draw(&self, brightness: f32) {
+        let density = self.effective_density();
+        let c = 0.6 + brightness * 0.4;
+
+        for layer in &self.layers {
+            let color = Color::new(c, c, c + 0.05, layer.alpha * density);
+            // Two copies side by side so the wrapped offset never shows a gap.
+            for x in [layer.offset - VIRTUAL_WIDTH, layer.offset] {
+                draw_texture_ex(
+                    &layer.texture,
+                    x,
+                    layer.y,
+                    color,
+                    DrawTextureParams {
+                        dest_size: Some(Vec2::new(VIRTUAL_WIDTH, layer.height)),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+}
+
+// ---------------------------
+// Puddles
+// ---------------------------
+const MAX_PUDDLES: usize = 8;
+
+struct Puddle {
+    x: f32,
+    y: f32,
+    size: f32,
+    level: f32,
+}
+
+impl Puddle {
+    fn new(level: f32) -> Self {
+        Self {
+            x: rand::gen_range(60.0, VIRTUAL_WIDTH - 60.0),
+            y: rand::gen_range(GROUND_LEVEL + 25.0, VIRTUAL_HEIGHT - 25.0),
+            size: rand::gen_range(70.0, 120.0),
+            level,
+        }
+    }
+
+    fn radius(&self) -> (f32, f32) {
+        let w = self.size * self.level.sqrt();
+        (w, w * 0.25)
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        let (rx, ry) = self.radius();
+        let dx = (point.x - self.x) / rx;
+        let dy = (point.y - self.y) / ry;
+        dx * dx + dy * dy < 1.0
+    }
+}
+
+pub struct Puddles {
+    puddles: Vec<Puddle>,
+    splash_sound: Sound,
+}
+
+impl Puddles {
+    pub fn new(splash_sound: Sound) -> Self {
+        // The night starts mid-storm, so the ground is already wet.
+        Self {
+            puddles: {
+                let mut puddles = Vec::with_capacity(MAX_PUDDLES);
+                puddles.extend((0..3).map(|_| Puddle::new(0.5)));
+                puddles
+            },
+            splash_sound,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32, rain: f32) {
+        if rain > 0.6 && self.puddles.len() < MAX_PUDDLES && rand::gen_range(0.0, 1.0) < dt * 0.2 {
+            self.puddles.push(Puddle::new(0.05));
+        }
+
+        for puddle in self.puddles.iter_mut() {
+            puddle.level = (puddle.level + (rain * 0.05 - 0.02) * dt).min(1.0);
+        }
+        self.puddles.retain(|p| p.level > 0.0);
+    }
+
+    // Speed multiplier for something standing at `point`.
+    pub fn slowdown_at(&self, point: Vec2) -> f32 {
+        self.puddles
+            .iter()
+            .filter(|p| p.contains(point))
+            .map(|p| 1.0 - 0.4 * p.level)
+            .fold(1.0, f32::min)
+    }
+
+    pub fn footstep(&self, point: Vec2, mixer: &Mixer) {
+        if self.puddles.iter().any(|p| p.contains(point)) {
+            mixer.play(&self.splash_sound, Bus::Sfx, 0.6);
+        }
+    }
+
+    pub fn draw(&self, brightness: f32) {
+        for puddle in &self.puddles {
+            let (rx, ry) = puddle.radius();
+            let c = 0.08 + brightness * 0.6;
+            draw_ellipse(puddle.x, puddle.y, rx, ry, 0.0, Color::new(c * 0.8, c * 0.9, c + 0.06, 0.85));
+            // A lightning flash shows up as a bright streak on the water.
+            if brightness > 0.0 {
+                draw_ellipse(puddle.x - rx * 0.2, puddle.y - ry * 0.2, rx * 0.5, ry * 0.3, 0.0, Color::new(0.9, 0.95, 1.0, brightness * 0.7));
+            }
+        }
+    }
+}
+
+// ---------------------------
+// WeatherController
+// ---------------------------
+#[derive(Clone, Copy, PartialEq)]
+pub enum WeatherState {
+    Clear,
+    Drizzle,
+    HeavyRain,
+    Thunderstorm,
+}
+
+#[derive(Clone, Copy)]
+struct WeatherParams {
+    raindrops: f32,
+    splash_chance: f32,
+    lightning_frequency: f32,
+    rain_volume: f32,
+    wind: f32,
+    fog: f32,
+}
+
+impl WeatherState {
+    fn params(self) -> WeatherParams {
+        match self {
+            WeatherState::Clear => WeatherParams { raindrops: 0.0, splash_chance: 0.0, lightning_frequency: 0.0, rain_volume: 0.0, wind: 20.0, fog: 0.15 },
+            WeatherState::Drizzle => WeatherParams { raindrops: 150.0, splash_chance: 0.04, lightning_frequency: 0.0, rain_volume: 0.2, wind: 40.0, fog: 0.6 },
+            WeatherState::HeavyRain => WeatherParams { raindrops: 600.0, splash_chance: 0.1, lightning_frequency: 0.3, rain_volume: 0.45, wind: 90.0, fog: 0.5 },
+            WeatherState::Thunderstorm => WeatherParams { raindrops: 900.0, splash_chance: 0.1, lightning_frequency: 1.0, rain_volume: 0.5, wind: 160.0, fog: 0.4 },
+        }
+    }
+
+    fn duration(self) -> f32 {
+        match self {
+            WeatherState::Clear => rand::gen_range(15.0, 25.0),
+            WeatherState::Drizzle => rand::gen_range(20.0, 35.0),
+            WeatherState::HeavyRain => rand::gen_range(30.0, 50.0),
+            WeatherState::Thunderstorm => rand::gen_range(40.0, 70.0),
+        }
+    }
+
+    // The storm only ever moves one step up or down, so it builds and calms gradually.
+    fn next(self) -> WeatherState {
+        let calmer = rand::gen_range(0.0, 1.0) < 0.5;
+        match (self, calmer) {
+            (WeatherState::Clear, _) => WeatherState::Drizzle,
+            (WeatherState::Drizzle, true) => WeatherState::Clear,
+            (WeatherState::Drizzle, false) => WeatherState::HeavyRain,
+            (WeatherState::HeavyRain, true) => WeatherState::Drizzle,
+            (WeatherState::HeavyRain, false) => WeatherState::Thunderstorm,
+            (WeatherState::Thunderstorm, _) => WeatherState::HeavyRain,
+        }
+    }
+}
+
+pub struct WeatherController {
+    state: WeatherState,
+    state_timer: f32,
+    state_duration: f32,
+    current: WeatherParams,
+    wind: Wind,
+    rain_sound: StereoSound,
+}
+
+impl WeatherController {
+    pub fn new(rain_sound: StereoSound) -> Self {
+        let state = WeatherState::Thunderstorm;
+        let current = state.params();
+        // Silent until the first update applies the mixer levels.
+        rain_sound.play_looped(0.0, 0.0);
+
+        Self {
+            state,
+            state_timer: 0.0,
+            state_duration: state.duration(),
+            current,
+            wind: Wind::new(),
+            rain_sound,
+        }
+    }
+
+    // Swaps the rain loop for a new recording without a break in the weather.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_rain_sound(&mut self, rain_sound: StereoSound) {
+        self.rain_sound.stop();
+        self.rain_sound = rain_sound;
+        self.rain_sound.play_looped(0.0, 0.0);
+    }
+
+    pub fn update(&mut self, dt: f32, mixer: &Mixer) {
+        self.state_timer += dt;
+        if self.state_timer > self.state_duration {
+            self.state = self.state.next();
+            self.state_timer = 0.0;
+            self.state_duration = self.state.duration();
+        }
+
+        // Ease towards the target over a few seconds instead of snapping.
+        let target = self.state.params();
+        let t = 1.0 - (-dt / 4.0).exp();
+        self.current.raindrops += (target.raindrops - self.current.raindrops) * t;
+        self.current.splash_chance += (target.splash_chance - self.current.splash_chance) * t;
+        self.current.lightning_frequency += (target.lightning_frequency - self.current.lightning_frequency) * t;
+        self.current.rain_volume += (target.rain_volume - self.current.rain_volume) * t;
+        self.current.wind += (target.wind - self.current.wind) * t;
+        self.current.fog += (target.fog - self.current.fog) * t;
+
+        self.wind.update(dt, self.current.wind);
+        // Lean the rain towards where the wind is blowing, never fully to one ear.
+        let pan = (self.wind.velocity / 200.0).clamp(-1.0, 1.0) * 0.6;
+        let volume = self.current.rain_volume * mixer.gain(Bus::Ambience) * mixer.duck_gain();
+        self.rain_sound.set_volume(volume, pan);
+    }
+
+    // Rain strength relative to a full thunderstorm.
+    pub fn rain_intensity(&self) -> f32 {
+        self.current.raindrops / WeatherState::Thunderstorm.params().raindrops
+    }
+
+    pub fn raindrop_count(&self) -> usize {
+        self.current.raindrops.round() as usize
+    }
+
+    pub fn splash_chance(&self) -> f32 {
+        self.current.splash_chance
+    }
+
+    pub fn lightning_frequency(&self) -> f32 {
+        self.current.lightning_frequency
+    }
+
+    pub fn wind(&self) -> f32 {
+        self.wind.velocity
+    }
+
+    pub fn fog_density(&self) -> f32 {
+        self.current.fog
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Counts heap allocations made on the current thread while `counting`
+    // is set, so the test harness's own threads don't get in the way.
+    struct CountingAllocator;
+
+    thread_local! {
+        static COUNTING: Cell<bool> = const { Cell::new(false) };
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if COUNTING.try_with(|c| c.get()).unwrap_or(false) {
+                let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            }
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            if COUNTING.try_with(|c| c.get()).unwrap_or(false) {
+                let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            }
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count_allocations(f: impl FnOnce()) -> usize {
+        ALLOCATIONS.with(|a| a.set(0));
+        COUNTING.with(|c| c.set(true));
+        f();
+        COUNTING.with(|c| c.set(false));
+        ALLOCATIONS.with(|a| a.get())
+    }
+
+    #[test]
+    fn rain_and_particles_do_not_allocate() {
+        let mut rain = Rain::new();
+        let mut particles = ParticleSystem::new();
+
+        let allocations = count_allocations(|| {
+            for frame in 0..600 {
+                // Swing between a drizzle and a full storm so the pools grow
+                // and shrink.
+                let count = if frame % 200 < 100 { MAX_RAINDROPS } else { 150 };
+                rain.update(1.0 / 60.0, count, 160.0, 0.1, &mut particles);
+                particles.update(1.0 / 60.0);
+            }
+        });
+        assert_eq!(allocations, 0);
+    }
+
+    #[test]
+    fn lightning_bolts_do_not_allocate() {
+        let mut bolt = LightningBolt::new();
+
+        let allocations = count_allocations(|| {
+            for _ in 0..1000 {
+                bolt.sky(rand::gen_range(0.0, 1.0));
+                bolt.strike(Vec2::new(rand::gen_range(0.0, VIRTUAL_WIDTH), VIRTUAL_HEIGHT));
+            }
+        });
+        assert_eq!(allocations, 0);
+    }
+}